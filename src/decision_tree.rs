@@ -0,0 +1,403 @@
+//! Lowers the arms of a `match` into a decision tree, the alternative to one
+//! `if` per arm. Each arm needs a list of runtime tests to pass, as worked out
+//! by the matcher. The tree makes one of those tests at a time and splits the
+//! arms into those still possible if it passes and those still possible if it
+//! fails, so that no test is ever made twice on the same path.
+//!
+//! Which test comes next is picked as in Maranget's "Compiling pattern
+//! matching to good decision trees": it has to be one the first remaining
+//! arm needs, and among those the one on the part of the scrutinee (the
+//! column) that the most arms look at.
+
+use crate::desugar::Arm;
+use crate::language::*;
+use crate::matcher::MatchReq;
+use crate::span::Span;
+
+/// The decision tree for `arms`, which evaluates to the result of the first
+/// arm that matches, or to `()` if none do.
+pub fn decision_tree<'sc>(arms: Vec<Arm<'sc>>, span: Span<'sc>) -> Expression<'sc> {
+    let rows = arms
+        .iter()
+        .enumerate()
+        .map(|(arm, Arm { tests, .. })| Row {
+            tests: tests.clone(),
+            arm,
+        })
+        .collect::<Vec<_>>();
+    lower(&rows, &arms).unwrap_or(Expression::Unit { span })
+}
+
+// An arm that is still possible, with the tests it needs that haven't been
+// made yet on the way here.
+#[derive(Debug, Clone)]
+struct Row<'sc> {
+    tests: Vec<MatchReq<'sc>>,
+    arm: usize,
+}
+
+// `None` if no arm is left
+fn lower<'sc>(rows: &[Row<'sc>], arms: &[Arm<'sc>]) -> Option<Expression<'sc>> {
+    let first = rows.first()?;
+    let arm = &arms[first.arm];
+    if first.tests.is_empty() {
+        // the first arm matches, unless its guard says otherwise
+        let body = arm.body();
+        return Some(match arm.guard_condition() {
+            None => body,
+            Some(guard) => Expression::IfExp {
+                condition: Box::new(guard),
+                then: Box::new(body),
+                r#else: lower(&rows[1..], arms).map(Box::new),
+                span: arm.span,
+            },
+        });
+    }
+    let test = select_test(rows);
+    let passed = rows
+        .iter()
+        .filter_map(|row| assume(row, &test, true))
+        .collect::<Vec<_>>();
+    let failed = rows
+        .iter()
+        .filter_map(|row| assume(row, &test, false))
+        .collect::<Vec<_>>();
+    Some(Expression::IfExp {
+        condition: Box::new(test.into_expression(arm.span)),
+        // the first row is still possible, so this can't be `None`
+        then: Box::new(lower(&passed, arms).unwrap_or(Expression::Unit { span: arm.span })),
+        r#else: lower(&failed, arms).map(Box::new),
+        span: arm.span,
+    })
+}
+
+// The next test to make: one the first row needs, on the column the most
+// rows test.
+fn select_test<'sc>(rows: &[Row<'sc>]) -> MatchReq<'sc> {
+    let tests = &rows[0].tests;
+    let relevance = |test: &MatchReq| match occurrence(test) {
+        Some(occurrence) => rows
+            .iter()
+            .filter(|row| {
+                row.tests
+                    .iter()
+                    .any(|other| occurrence_of(other, occurrence))
+            })
+            .count(),
+        None => 0,
+    };
+    let mut best: Option<(&MatchReq, usize)> = None;
+    for (i, test) in tests.iter().enumerate() {
+        if !is_ready(&tests[..i], test) {
+            continue;
+        }
+        let score = relevance(test);
+        if best.map_or(true, |(_, best_score)| score > best_score) {
+            best = Some((test, score));
+        }
+    }
+    best.map(|(test, _)| test.clone())
+        .expect("the first test of a row is always ready")
+}
+
+// Whether `test` can be made before the tests of its row that come before
+// it: those that make sure a projection in it is valid have to come first,
+// and an or-pattern could be testing anything, so nothing moves past one.
+fn is_ready(earlier: &[MatchReq], test: &MatchReq) -> bool {
+    let occurrence = match occurrence(test) {
+        Some(occurrence) => occurrence,
+        None => return earlier.is_empty(),
+    };
+    earlier.iter().all(|earlier| match earlier {
+        MatchReq::Any(_) => false,
+        MatchReq::StructKind(exp, _)
+        | MatchReq::Variant { exp, .. }
+        | MatchReq::Length { exp, .. } => !projects_out_of(occurrence, exp),
+        MatchReq::Eq(..) | MatchReq::Range { .. } => true,
+    })
+}
+
+// the part of the scrutinee `test` looks at
+fn occurrence<'a, 'sc>(test: &'a MatchReq<'sc>) -> Option<&'a Expression<'sc>> {
+    match test {
+        MatchReq::Eq(_, exp)
+        | MatchReq::StructKind(exp, _)
+        | MatchReq::Variant { exp, .. }
+        | MatchReq::Length { exp, .. }
+        | MatchReq::Range { exp, .. } => Some(exp),
+        MatchReq::Any(_) => None,
+    }
+}
+
+fn occurrence_of(test: &MatchReq, exp: &Expression) -> bool {
+    occurrence(test) == Some(exp)
+}
+
+// whether `exp` is a projection, however deep, out of `prefix`
+fn projects_out_of(exp: &Expression, prefix: &Expression) -> bool {
+    match exp {
+        Expression::TupleIndex { prefix: inner, .. }
+        | Expression::ArrayIndex { prefix: inner, .. }
+        | Expression::SubfieldExpression { prefix: inner, .. }
+        | Expression::IndexFromEnd { prefix: inner, .. }
+        | Expression::VariantPayload { prefix: inner, .. } => {
+            inner.as_ref() == prefix || projects_out_of(inner, prefix)
+        }
+        _ => false,
+    }
+}
+
+// `row` once `test` is known to have `passed` or not, without the tests that
+// are decided by that, or `None` if the row can't match anymore
+fn assume<'sc>(row: &Row<'sc>, test: &MatchReq<'sc>, passed: bool) -> Option<Row<'sc>> {
+    let mut tests = vec![];
+    for other in row.tests.iter() {
+        match implied(test, passed, other) {
+            Some(true) => {}
+            Some(false) => return None,
+            None => tests.push(other.clone()),
+        }
+    }
+    Some(Row {
+        tests,
+        arm: row.arm,
+    })
+}
+
+// what the outcome of `test` says about the outcome of `other`, if anything
+fn implied(test: &MatchReq, passed: bool, other: &MatchReq) -> Option<bool> {
+    if test == other {
+        return Some(passed);
+    }
+    if occurrence(test).is_none() || occurrence(test) != occurrence(other) {
+        return None;
+    }
+    let (values, other_values) = (Values::of(test)?, Values::of(other)?);
+    let (known_true, known_false) = if passed {
+        (values.within(&other_values), values.disjoint(&other_values))
+    } else {
+        (
+            values.complement(&other_values),
+            other_values.within(&values),
+        )
+    };
+    match (known_true, known_false) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    }
+}
+
+// The values that pass a test, as far as they can be compared with those of
+// other tests on the same part of the scrutinee.
+#[derive(Debug, Clone, PartialEq)]
+enum Values<'sc> {
+    // inclusive, and empty if the first is bigger
+    Integers(u64, u64),
+    Boolean(bool),
+    Struct(&'sc str),
+    Variant(&'sc str, &'sc str),
+    // the shortest length, and the longest if there is one
+    Lengths(usize, Option<usize>),
+}
+
+impl<'sc> Values<'sc> {
+    fn of(test: &MatchReq<'sc>) -> Option<Self> {
+        match test {
+            MatchReq::Eq(
+                Expression::Literal {
+                    value: Literal::Boolean(b),
+                    ..
+                },
+                _,
+            ) => Some(Values::Boolean(*b)),
+            MatchReq::Eq(Expression::Literal { value, .. }, _) => {
+                let n = value.integer_value()?;
+                Some(Values::Integers(n, n))
+            }
+            MatchReq::Eq(..) | MatchReq::Any(_) => None,
+            MatchReq::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                let low = match start {
+                    Some(start) => start.integer_value()?,
+                    None => 0,
+                };
+                match end {
+                    Some(end) => {
+                        let end = end.integer_value()?;
+                        match (inclusive, end.checked_sub(1)) {
+                            (true, _) => Some(Values::Integers(low, end)),
+                            (false, Some(high)) => Some(Values::Integers(low, high)),
+                            // `..0`, which nothing passes
+                            (false, None) => Some(Values::Integers(1, 0)),
+                        }
+                    }
+                    None => Some(Values::Integers(low, u64::MAX)),
+                }
+            }
+            MatchReq::StructKind(_, struct_name) => Some(Values::Struct(struct_name.primary_name)),
+            MatchReq::Variant {
+                enum_name,
+                variant_name,
+                ..
+            } => Some(Values::Variant(
+                enum_name.primary_name,
+                variant_name.primary_name,
+            )),
+            MatchReq::Length {
+                length, at_least, ..
+            } => Some(Values::Lengths(
+                *length,
+                if *at_least { None } else { Some(*length) },
+            )),
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        matches!(self, Values::Integers(low, high) if low > high)
+    }
+
+    // whether every value in `self` is also in `other`
+    fn within(&self, other: &Values) -> bool {
+        match (self, other) {
+            _ if self.is_empty() => true,
+            (Values::Integers(low, high), Values::Integers(other_low, other_high)) => {
+                other_low <= low && high <= other_high
+            }
+            (Values::Lengths(low, high), Values::Lengths(other_low, other_high)) => {
+                other_low <= low
+                    && match (high, other_high) {
+                        (_, None) => true,
+                        (Some(high), Some(other_high)) => high <= other_high,
+                        (None, Some(_)) => false,
+                    }
+            }
+            _ => self == other,
+        }
+    }
+
+    // whether no value is in both
+    fn disjoint(&self, other: &Values) -> bool {
+        match (self, other) {
+            _ if self.is_empty() || other.is_empty() => true,
+            (Values::Integers(low, high), Values::Integers(other_low, other_high)) => {
+                high < other_low || other_high < low
+            }
+            (Values::Lengths(low, high), Values::Lengths(other_low, other_high)) => {
+                high.map_or(false, |high| high < *other_low)
+                    || other_high.map_or(false, |other_high| other_high < *low)
+            }
+            (Values::Boolean(b), Values::Boolean(other_b)) => b != other_b,
+            (Values::Struct(name), Values::Struct(other_name)) => name != other_name,
+            (Values::Variant(..), Values::Variant(..)) => self != other,
+            _ => false,
+        }
+    }
+
+    // whether every value is in one or the other
+    fn complement(&self, other: &Values) -> bool {
+        matches!(
+            (self, other),
+            (Values::Boolean(b), Values::Boolean(other_b)) if b != other_b
+        )
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        desugar::{desugar_with, Lowering},
+        interpreter::{interpret, interpret_with},
+        language::{Expression, Namespace, Node},
+        parser::parse,
+        value::Value,
+    };
+
+    // how many tests the desugared node makes on its longest path
+    fn depth(exp: &Expression) -> usize {
+        match exp {
+            Expression::IfExp { then, r#else, .. } => {
+                1 + depth(then).max(r#else.as_ref().map_or(0, |r#else| depth(r#else)))
+            }
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn tree_tests_each_field_once() {
+        let src = "
+            match (x, y) {
+                (0, 0) => 0,
+                (0, _) => 1,
+                (_, 0) => 2,
+                (_, _) => 3,
+            }
+        ";
+        let node = parse(src).unwrap().nodes.remove(0);
+        let namespace = Namespace::default();
+        let (node, _) = desugar_with(node, &namespace, Lowering::DecisionTree).unwrap();
+        let tree = match node {
+            Node::Expression(Expression::CodeBlock { mut contents, .. }) => {
+                match contents.contents.pop() {
+                    Some(Node::Expression(tree)) => tree,
+                    node => panic!("expected a decision tree, found {:?}", node),
+                }
+            }
+            node => panic!("expected a block, found {:?}", node),
+        };
+        // the length check on the tuple, then one test each for `x` and `y`
+        assert_eq!(depth(&tree), 3);
+    }
+
+    #[test]
+    fn tree_agrees_with_chain() {
+        let programs = [
+            "
+            struct Point { x: u32, y: u32 }
+            let foo = { Point { x: 5, y: 7 } };
+            match foo {
+                Point { x, y: 0 } => x,
+                Point { x: 0, y } => y,
+                Point { x: 5, y: 7 } if false => 1,
+                Point { x: 5, y } => y,
+                Point { .. } => 2,
+            }
+            ",
+            "
+            enum Shape { Dot, Circle(u32), Rect(u32, u32) }
+            let shapes = { [Shape::Rect(2, 1), Shape::Circle(4)] };
+            match shapes {
+                [] => 0,
+                [Shape::Dot, ..] => 1,
+                [Shape::Rect(1, h) | Shape::Rect(h, 1), ..] => h,
+                [_, Shape::Circle(r)] => r,
+                [..] => 9,
+            }
+            ",
+            "
+            let n = { 200u8 };
+            match (n, true) {
+                (0u8..=9u8, _) => 0u8,
+                (10u8..100u8, true) => 1u8,
+                (100u8.., false) => 2u8,
+                (m @ 100u8.., true) => m,
+                _ => 4u8,
+            }
+            ",
+        ];
+        for src in programs.iter() {
+            let chain = interpret(parse(src).unwrap());
+            let tree = interpret_with(parse(src).unwrap(), Lowering::DecisionTree);
+            assert!(chain.is_ok(), "{:?}", chain);
+            assert_eq!(chain, tree);
+        }
+        assert_eq!(
+            interpret_with(parse(programs[2]).unwrap(), Lowering::DecisionTree),
+            Ok(Value::U8(200))
+        );
+    }
+}
@@ -1,155 +1,991 @@
-use crate::language::constructors::{boolean, literal, variable_scrutinee};
+use std::collections::BTreeSet;
+use std::mem;
+
+use crate::decision_tree::decision_tree;
+use crate::error::{DesugarError, DesugarWarning, MatchError};
 use crate::language::*;
 use crate::matcher::*;
+use crate::span::Span;
+use crate::usefulness::{check_exhaustiveness, check_redundancy};
+
+/// The desugared node, along with warnings about the `match` it came from.
+pub type DesugarResult<'sc> = Result<(Node<'sc>, Vec<DesugarWarning<'sc>>), DesugarError<'sc>>;
+
+/// How the arms of a `match` are turned into `if`s. A `match` on integer
+/// literals becomes a `Switch` instead, whichever is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lowering {
+    /// One `if` per arm, in order, each testing the whole of its pattern.
+    #[default]
+    Chain,
+    /// A decision tree, which tests each part of the scrutinee at most once
+    /// on the way to an arm.
+    DecisionTree,
+}
+
+/// Desugars every `match` in `node`. A destructuring `let` becomes several
+/// declarations, which belong in the scope of the `let` itself rather than
+/// in a single node, so one on its own is left as it is: `desugar_tree`
+/// expands those too.
+pub fn desugar<'sc>(node: Node<'sc>, namespace: &Namespace<'sc>) -> DesugarResult<'sc> {
+    desugar_with(node, namespace, Lowering::default())
+}
+
+/// Like `desugar`, with the arms of a `match` lowered as `lowering` says.
+pub fn desugar_with<'sc>(
+    node: Node<'sc>,
+    namespace: &Namespace<'sc>,
+    lowering: Lowering,
+) -> DesugarResult<'sc> {
+    let mut desugarer = Desugarer::new(namespace, lowering);
+    let node = match node {
+        // the scrutinee of this `match` is evaluated right where the node
+        // is, so unlike a nested one it can use the values in `namespace`
+        Node::MatchStatement(MatchStatement {
+            primary,
+            branches,
+            span,
+        }) => desugarer.desugar_match(primary, branches, span, Some(namespace))?,
+        mut node => {
+            desugarer.node(&mut node)?;
+            node
+        }
+    };
+    Ok((node, desugarer.warnings))
+}
+
+/// The desugared tree, along with warnings about all of its matches.
+pub type DesugarTreeResult<'sc> = Result<(Tree<'sc>, Vec<DesugarWarning<'sc>>), DesugarError<'sc>>;
+
+/// Desugars every `match` in `tree`, however deeply it's nested, so that
+/// what's left is only `if`s, blocks and switches.
+pub fn desugar_tree<'sc>(tree: Tree<'sc>) -> DesugarTreeResult<'sc> {
+    desugar_tree_with(tree, Lowering::default())
+}
+
+/// Like `desugar_tree`, with the arms of a `match` lowered as `lowering`
+/// says.
+pub fn desugar_tree_with<'sc>(tree: Tree<'sc>, lowering: Lowering) -> DesugarTreeResult<'sc> {
+    let mut desugarer = Desugarer::new(&Namespace::default(), lowering);
+    let mut nodes = tree.nodes;
+    desugarer.nodes(&mut nodes)?;
+    Ok((Tree { nodes }, desugarer.warnings))
+}
+
+// Finds the matches inside a node, statements and expressions alike, and
+// replaces each with its desugared form.
+struct Desugarer<'sc> {
+    // only the types, which are added to as their declarations are passed
+    // and forgotten at the end of the block they're declared in: by the time a nested `match` is reached, the variables it mentions may
+    // have been shadowed or reassigned, so its scrutinee is matched
+    // symbolically unless it's a constant
+    types: Namespace<'sc>,
+    lowering: Lowering,
+    // shared, so that nested matches never bind the same hidden name
+    names: NameGenerator,
+    warnings: Vec<DesugarWarning<'sc>>,
+}
+
+impl<'sc> Desugarer<'sc> {
+    fn new(namespace: &Namespace<'sc>, lowering: Lowering) -> Self {
+        Desugarer {
+            types: namespace.types(),
+            lowering,
+            names: NameGenerator::default(),
+            warnings: vec![],
+        }
+    }
+
+    // a destructuring `let` becomes several declarations, in the same scope
+    fn nodes(&mut self, nodes: &mut Vec<Node<'sc>>) -> Result<(), DesugarError<'sc>> {
+        let mut desugared = Vec::with_capacity(nodes.len());
+        for mut node in nodes.drain(..) {
+            match node {
+                Node::Declaration(Declaration::DestructuringDeclaration(mut declaration)) => {
+                    self.expression(&mut declaration.body)?;
+                    desugared.extend(desugar_destructuring_declaration(
+                        declaration,
+                        &self.types,
+                        &mut self.names,
+                    )?);
+                }
+                _ => {
+                    self.node(&mut node)?;
+                    desugared.push(node);
+                }
+            }
+        }
+        *nodes = desugared;
+        Ok(())
+    }
+
+    // the types declared in a block are only visible inside it
+    fn block(&mut self, nodes: &mut Vec<Node<'sc>>) -> Result<(), DesugarError<'sc>> {
+        let types = self.types.clone();
+        let result = self.nodes(nodes);
+        self.types = types;
+        result
+    }
+
+    fn node(&mut self, node: &mut Node<'sc>) -> Result<(), DesugarError<'sc>> {
+        match node {
+            Node::Declaration(Declaration::VariableDeclaration(declaration)) => {
+                self.expression(&mut declaration.body)
+            }
+            // on its own, with nowhere to put its declarations
+            Node::Declaration(Declaration::DestructuringDeclaration(declaration)) => {
+                self.expression(&mut declaration.body)
+            }
+            Node::Declaration(Declaration::Reassignment(reassignment)) => {
+                self.expression(&mut reassignment.lhs)?;
+                self.expression(&mut reassignment.rhs)
+            }
+            Node::Declaration(Declaration::EnumDeclaration(declaration)) => {
+                self.types.declare_enum(declaration.clone());
+                Ok(())
+            }
+            Node::Declaration(Declaration::StructDeclaration(declaration)) => {
+                self.types.declare_struct(declaration.clone());
+                Ok(())
+            }
+            Node::Expression(exp) => self.expression(exp),
+            Node::WhileLoop(while_loop) => {
+                self.expression(&mut while_loop.condition)?;
+                self.block(&mut while_loop.body.contents)
+            }
+            Node::ReturnStatement(return_statement) => self.expression(&mut return_statement.expr),
+            Node::IfExpression(if_expression) => {
+                self.expression(&mut if_expression.primary)?;
+                self.expression(&mut if_expression.left)?;
+                self.optional_expression(&mut if_expression.right)
+            }
+            Node::Switch(switch) => {
+                self.expression(&mut switch.primary)?;
+                self.expressions(&mut switch.blocks)?;
+                self.optional_expression(&mut switch.default)
+            }
+            Node::MatchStatement(MatchStatement {
+                primary,
+                branches,
+                span,
+            }) => {
+                let span = *span;
+                let primary = mem::replace(primary, Expression::Unit { span });
+                let branches = mem::take(branches);
+                *node = self.desugar_match(primary, branches, span, None)?;
+                Ok(())
+            }
+        }
+    }
+
+    // inside out, so that the arms are already desugared, and against
+    // `namespace` if given or only the types otherwise
+    fn desugar_match(
+        &mut self,
+        mut primary: Expression<'sc>,
+        mut branches: Vec<MatchBranch<'sc>>,
+        span: Span<'sc>,
+        namespace: Option<&Namespace<'sc>>,
+    ) -> Result<Node<'sc>, DesugarError<'sc>> {
+        self.expression(&mut primary)?;
+        self.branches(&mut branches)?;
+        let (node, warnings) = desugar_match_statement(
+            primary,
+            branches,
+            span,
+            namespace.unwrap_or(&self.types),
+            self.lowering,
+            &mut self.names,
+        )?;
+        self.warnings.extend(warnings);
+        Ok(node)
+    }
+
+    fn branches(&mut self, branches: &mut [MatchBranch<'sc>]) -> Result<(), DesugarError<'sc>> {
+        for branch in branches.iter_mut() {
+            self.optional_expression(&mut branch.guard)?;
+            self.expression(&mut branch.result)?;
+        }
+        Ok(())
+    }
+
+    fn expressions(&mut self, exps: &mut [Expression<'sc>]) -> Result<(), DesugarError<'sc>> {
+        exps.iter_mut().try_for_each(|exp| self.expression(exp))
+    }
 
-pub fn desugar<'sc>(node: Node<'sc>, namespace: &Namespace<'sc>) -> Result<Node<'sc>, String> {
+    fn optional_expression(
+        &mut self,
+        exp: &mut Option<Expression<'sc>>,
+    ) -> Result<(), DesugarError<'sc>> {
+        exp.iter_mut().try_for_each(|exp| self.expression(exp))
+    }
+
+    fn expression(&mut self, exp: &mut Expression<'sc>) -> Result<(), DesugarError<'sc>> {
+        match exp {
+            Expression::Literal { .. }
+            | Expression::VariableExpression { .. }
+            | Expression::Unit { .. } => Ok(()),
+            Expression::BinOp { left, right, .. } => {
+                self.expression(left)?;
+                self.expression(right)
+            }
+            Expression::Array {
+                contents: elems, ..
+            }
+            | Expression::Tuple { elems, .. }
+            | Expression::EnumInstantiation { payload: elems, .. } => self.expressions(elems),
+            Expression::StructExpression {
+                struct_name,
+                fields,
+                span,
+            } => {
+                check_struct_expression(struct_name, fields, *span, &self.types)?;
+                fields
+                    .iter_mut()
+                    .try_for_each(|field| self.expression(&mut field.value))
+            }
+            Expression::CodeBlock { contents, .. } => self.block(&mut contents.contents),
+            Expression::IfExp {
+                condition,
+                then,
+                r#else,
+                ..
+            } => {
+                self.expression(condition)?;
+                self.expression(then)?;
+                r#else
+                    .iter_mut()
+                    .try_for_each(|r#else| self.expression(r#else))
+            }
+            Expression::TupleIndex { prefix, .. }
+            | Expression::ArrayIndex { prefix, .. }
+            | Expression::SubfieldExpression { prefix, .. }
+            | Expression::IndexFromEnd { prefix, .. }
+            | Expression::LengthCheck { prefix, .. }
+            | Expression::StructKindCheck { prefix, .. }
+            | Expression::VariantCheck { prefix, .. }
+            | Expression::VariantPayload { prefix, .. } => self.expression(prefix),
+            Expression::Match {
+                primary,
+                branches,
+                span,
+            } => {
+                let span = *span;
+                let primary = mem::replace(primary.as_mut(), Expression::Unit { span });
+                let branches = mem::take(branches);
+                let node = self.desugar_match(primary, branches, span, None)?;
+                *exp = into_expression(node, span);
+                Ok(())
+            }
+        }
+    }
+}
+
+// the value of a desugared `match`, as an expression
+fn into_expression<'sc>(node: Node<'sc>, span: Span<'sc>) -> Expression<'sc> {
     match node {
-        Node::MatchStatement(MatchStatement { primary, branches }) => {
-            desugar_match_statement(primary, branches, namespace)
+        Node::Expression(exp) => exp,
+        Node::IfExpression(IfExpression {
+            primary,
+            left,
+            right,
+            span,
+        }) => Expression::IfExp {
+            condition: Box::new(primary),
+            then: Box::new(left),
+            r#else: right.map(Box::new),
+            span,
+        },
+        node => Expression::CodeBlock {
+            contents: CodeBlock {
+                contents: vec![node],
+            },
+            span,
+        },
+    }
+}
+
+/// An arm of a `match`, with its pattern broken down by the matcher into the
+/// tests it needs and the variables it binds.
+#[derive(Debug, Clone)]
+pub struct Arm<'sc> {
+    pub tests: MatchReqMap<'sc>,
+    pub bindings: MatchImplMap<'sc>,
+    pub guard: Option<Expression<'sc>>,
+    pub result: Expression<'sc>,
+    pub span: Span<'sc>,
+}
+
+impl<'sc> Arm<'sc> {
+    // everything generated for an arm carries the span of that arm, so that
+    // errors in desugared code point back at the user's `match`
+    fn binding_declarations(&self) -> Vec<Node<'sc>> {
+        // the bindings only ever project out of the (hidden) scrutinee, so
+        // they can't see each other
+        debug_assert!(self.bindings.iter().all(|(_, right_impl)| {
+            self.bindings
+                .iter()
+                .all(|(left_impl, _)| !refers_to(right_impl, left_impl))
+        }));
+        self.bindings
+            .iter()
+            .map(|(left_impl, right_impl)| {
+                Node::Declaration(Declaration::VariableDeclaration(VariableDeclaration {
+                    name: Ident::new(left_impl, self.span),
+                    body: right_impl.clone(),
+                    is_mutable: false,
+                    span: self.span,
+                }))
+            })
+            .collect()
+    }
+
+    /// The guard, in a block of its own that binds the pattern's variables
+    /// for it, or `None` if the arm has no guard.
+    pub fn guard_condition(&self) -> Option<Expression<'sc>> {
+        let guard = self.guard.as_ref()?;
+        let mut contents = self.binding_declarations();
+        contents.push(Node::Expression(guard.clone()));
+        Some(Expression::CodeBlock {
+            contents: CodeBlock { contents },
+            span: self.span,
+        })
+    }
+
+    /// The result, in a block that binds the pattern's variables.
+    pub fn body(&self) -> Expression<'sc> {
+        let mut contents = self.binding_declarations();
+        contents.push(Node::Expression(self.result.clone()));
+        Expression::CodeBlock {
+            contents: CodeBlock { contents },
+            span: self.span,
         }
-        node => Ok(node),
     }
 }
 
 fn desugar_match_statement<'sc>(
     primary: Expression<'sc>,
     branches: Vec<MatchBranch<'sc>>,
+    match_span: Span<'sc>,
     namespace: &Namespace<'sc>,
-) -> Result<Node<'sc>, String> {
-    let mut matched_branches = vec![];
-    for MatchBranch { condition, result } in branches.iter() {
-        let matches = match condition {
-            MatchScrutinee::CatchAll => Some((vec![], vec![])),
-            MatchScrutinee::Scrutinee(scrutinee) => matcher(&primary, scrutinee, namespace),
-        };
-        match matches {
-            Some((match_req_map, match_impl_map)) => {
-                matched_branches.push((result.to_owned(), match_req_map, match_impl_map))
-            }
-            None => return Err("Incompatible match provided".to_string()),
+    lowering: Lowering,
+    names: &mut NameGenerator,
+) -> DesugarResult<'sc> {
+    if branches.is_empty() {
+        return Err(DesugarError::EmptyMatch { span: match_span });
+    }
+    for branch in branches.iter() {
+        if let MatchScrutinee::Scrutinee(scrutinee) = &branch.condition {
+            check_duplicate_fields(scrutinee)?;
+            check_duplicate_bindings(scrutinee)?;
+            check_declarations(scrutinee, namespace)?;
         }
     }
+    if let Some(witness) = check_exhaustiveness(&branches, namespace) {
+        return Err(DesugarError::NonExhaustive {
+            witness: Box::new(witness),
+            span: match_span,
+        });
+    }
+    let warnings = check_redundancy(&branches, namespace);
+    let switch_cases = switch_cases(&branches);
 
-    let mut if_statement = None;
+    let is_symbolic = is_symbolic(&primary, namespace)?;
+    let (primary, scrutinee_declaration) = if is_symbolic {
+        let (primary, declaration) = bind_scrutinee(primary, names);
+        (primary, Some(declaration))
+    } else {
+        (primary, None)
+    };
 
-    for (result, match_req_map, match_impl_map) in matched_branches.into_iter().rev() {
-        let mut conditional = None;
-        for (left_req, right_req) in match_req_map.iter() {
-            let condition = Expression::BinOp {
-                op2: Op2::Eq,
-                left: Box::new(left_req.clone()),
-                right: Box::new(right_req.clone()),
-            };
-            match conditional {
-                None => {
-                    conditional = Some(condition);
-                }
-                Some(the_conditional) => {
-                    conditional = Some(Expression::BinOp {
-                        op2: Op2::And,
-                        left: Box::new(the_conditional),
-                        right: Box::new(condition),
-                    });
-                }
+    let mut arms = vec![];
+    for MatchBranch {
+        condition,
+        guard,
+        result,
+        span,
+    } in branches.into_iter()
+    {
+        let (tests, bindings) = match &condition {
+            MatchScrutinee::CatchAll => (vec![], vec![]),
+            MatchScrutinee::Scrutinee(scrutinee) if is_symbolic => {
+                symbolic_matcher(&primary, scrutinee)?
             }
+            MatchScrutinee::Scrutinee(scrutinee) => matcher(&primary, scrutinee, namespace)?,
+        };
+        arms.push(Arm {
+            tests,
+            bindings,
+            guard,
+            result,
+            span,
+        });
+    }
+
+    let node = match (switch_cases, lowering) {
+        (Some(switch_cases), _) => lower_switch(primary, switch_cases, arms, match_span),
+        (None, Lowering::Chain) => lower_chain(arms),
+        (None, Lowering::DecisionTree) => Node::Expression(decision_tree(arms, match_span)),
+    };
+    match scrutinee_declaration {
+        None => Ok((node, warnings)),
+        Some(declaration) => {
+            let block = Expression::CodeBlock {
+                contents: CodeBlock {
+                    contents: vec![
+                        Node::Declaration(Declaration::VariableDeclaration(declaration)),
+                        node,
+                    ],
+                },
+                span: match_span,
+            };
+            Ok((Node::Expression(block), warnings))
         }
+    }
+}
 
-        let mut code_block_stmts = vec![];
-        for (left_impl, right_impl) in match_impl_map.into_iter() {
-            code_block_stmts.push(Node::Declaration(Declaration::VariableDeclaration(
-                VariableDeclaration {
-                    name: Ident {
-                        primary_name: left_impl,
-                    },
-                    body: right_impl.clone(),
-                    is_mutable: false,
+// a scrutinee that the namespace can't evaluate is matched symbolically, and
+// is evaluated once at runtime, before anything is matched against it
+fn is_symbolic<'sc>(
+    primary: &Expression<'sc>,
+    namespace: &Namespace<'sc>,
+) -> Result<bool, DesugarError<'sc>> {
+    match eval_exp(primary, namespace) {
+        Ok(_) => Ok(false),
+        Err(MatchError::UnboundVariable { .. }) | Err(MatchError::UnsupportedExpression { .. }) => {
+            Ok(true)
+        }
+        Err(err) => Err(err.into()),
+    }
+}
+
+// a declaration of a hidden variable holding `primary`, along with the
+// variable to match against instead
+fn bind_scrutinee<'sc>(
+    primary: Expression<'sc>,
+    names: &mut NameGenerator,
+) -> (Expression<'sc>, VariableDeclaration<'sc>) {
+    let span = primary.span();
+    let name = names.fresh("scrutinee", span);
+    let declaration = VariableDeclaration {
+        name: name.clone(),
+        body: primary,
+        is_mutable: false,
+        span,
+    };
+    (Expression::VariableExpression { name, span }, declaration)
+}
+
+// a destructuring `let` becomes a declaration of each variable its pattern
+// binds
+fn desugar_destructuring_declaration<'sc>(
+    declaration: DestructuringDeclaration<'sc>,
+    namespace: &Namespace<'sc>,
+    names: &mut NameGenerator,
+) -> Result<Vec<Node<'sc>>, DesugarError<'sc>> {
+    let DestructuringDeclaration {
+        pattern,
+        body,
+        is_mutable,
+        span,
+    } = declaration;
+    check_duplicate_fields(&pattern)?;
+    check_duplicate_bindings(&pattern)?;
+    check_declarations(&pattern, namespace)?;
+    let branch = MatchBranch {
+        condition: MatchScrutinee::Scrutinee(pattern.clone()),
+        guard: None,
+        result: Expression::Unit { span },
+        span,
+    };
+    if let Some(witness) = check_exhaustiveness(&[branch], namespace) {
+        return Err(DesugarError::RefutablePattern {
+            witness: Box::new(witness),
+            span,
+        });
+    }
+
+    // the pattern matches every value, so only the bindings are needed and
+    // not the tests
+    let mut nodes = vec![];
+    let bindings = if is_symbolic(&body, namespace)? {
+        let (primary, declaration) = bind_scrutinee(body, names);
+        nodes.push(Node::Declaration(Declaration::VariableDeclaration(
+            declaration,
+        )));
+        symbolic_matcher(&primary, &pattern)?.1
+    } else {
+        matcher(&body, &pattern, namespace)?.1
+    };
+    for (name, body) in bindings {
+        nodes.push(Node::Declaration(Declaration::VariableDeclaration(
+            VariableDeclaration {
+                name: Ident::new(name, span),
+                body,
+                is_mutable,
+                span,
+            },
+        )));
+    }
+    Ok(nodes)
+}
+
+// one `if` per arm, built from the last arm back
+fn lower_chain(arms: Vec<Arm>) -> Node {
+    let mut if_statement = None;
+
+    for arm in arms.into_iter().rev() {
+        let span = arm.span;
+        let mut conditional = conjunction(arm.tests.clone(), span);
+        // the guard is only evaluated once the pattern is known to match
+        if let Some(guard) = arm.guard_condition() {
+            conditional = Some(match conditional {
+                None => guard,
+                Some(conditional) => Expression::BinOp {
+                    op2: Op2::And,
+                    left: Box::new(conditional),
+                    right: Box::new(guard),
+                    span,
                 },
-            )));
+            });
         }
-        code_block_stmts.push(Node::Expression(result.clone()));
+        let block = arm.body();
 
         match if_statement {
             None => {
-                let block = Expression::CodeBlock {
-                    contents: CodeBlock {
-                        contents: code_block_stmts,
-                    },
-                };
                 if_statement = match conditional {
                     None => Some(Node::Expression(block)),
                     Some(conditional) => Some(Node::IfExpression(IfExpression {
                         primary: conditional,
                         left: block,
                         right: None,
+                        span,
                     })),
                 };
             }
-            Some(Node::Expression(Expression::CodeBlock {
-                contents: CodeBlock {
-                    contents: the_contents,
-                },
-            })) => {
-                let left = Expression::CodeBlock {
-                    contents: CodeBlock {
-                        contents: code_block_stmts,
-                    },
-                };
-                let right = Some(Expression::CodeBlock {
-                    contents: CodeBlock {
-                        contents: the_contents,
-                    },
-                });
-                if_statement = match conditional {
-                    None => Some(Node::IfExpression(IfExpression {
-                        primary: literal(boolean(true)),
-                        left,
-                        right,
-                    })),
-                    Some(conditional) => Some(Node::IfExpression(IfExpression {
-                        primary: conditional,
-                        left,
-                        right,
-                    })),
-                };
+            Some(Node::Expression(right @ Expression::CodeBlock { .. })) => {
+                if_statement = Some(Node::IfExpression(IfExpression {
+                    primary: conditional.unwrap_or(Expression::Literal {
+                        value: Literal::Boolean(true),
+                        span,
+                    }),
+                    left: block,
+                    right: Some(right),
+                    span,
+                }));
             }
             Some(Node::IfExpression(IfExpression {
                 primary,
                 left,
                 right,
+                span: the_span,
             })) => {
                 if_statement = Some(Node::IfExpression(IfExpression {
-                    primary: conditional.unwrap(),
-                    left: Expression::CodeBlock {
-                        contents: CodeBlock {
-                            contents: code_block_stmts,
-                        },
-                    },
+                    // an arm that matches everything can still be followed
+                    // by (unreachable) arms
+                    primary: conditional.unwrap_or(Expression::Literal {
+                        value: Literal::Boolean(true),
+                        span,
+                    }),
+                    left: block,
                     right: Some(Expression::IfExp {
                         condition: Box::new(primary),
                         then: Box::new(left),
                         r#else: right.map(Box::new),
+                        span: the_span,
                     }),
+                    span,
                 }));
             }
             _ => unimplemented!(),
         }
     }
 
-    match if_statement {
-        None => Err("something went wrong...".to_string()),
-        Some(if_statement) => Ok(if_statement),
+    if_statement.expect("a match has arms")
+}
+
+// the fewest distinct integers a `match` needs to be worth a jump table
+const MIN_SWITCH_CASES: usize = 4;
+
+// The integers tested for by the leading arms of a `match` that can be a
+// `Switch`, where every arm up to one that matches everything is unguarded and
+// tests for integer literals of a single width.
+struct SwitchCases<'sc> {
+    cases: Vec<Vec<Literal<'sc>>>,
+    // whether the arm after the cases matches everything, and so runs for
+    // any value the table has no block for
+    has_default: bool,
+}
+
+// `None` unless the `match` is one on integer literals, and they are close
+// enough together for a table from the smallest to the largest to be at
+// most half empty
+fn switch_cases<'sc>(branches: &[MatchBranch<'sc>]) -> Option<SwitchCases<'sc>> {
+    let mut cases = vec![];
+    let mut has_default = false;
+    for branch in branches.iter() {
+        if branch.guard.is_some() {
+            return None;
+        }
+        match &branch.condition {
+            MatchScrutinee::CatchAll
+            | MatchScrutinee::Scrutinee(Scrutinee::VariableExpression { .. })
+            | MatchScrutinee::Scrutinee(Scrutinee::Wildcard { .. }) => {
+                // any arms after this one are unreachable
+                has_default = true;
+                break;
+            }
+            MatchScrutinee::Scrutinee(scrutinee) => cases.push(integer_literals(scrutinee)?),
+        }
+    }
+
+    let literals: Vec<&Literal> = cases.iter().flatten().collect();
+    let first = literals.first()?;
+    if literals
+        .iter()
+        .any(|literal| mem::discriminant(*literal) != mem::discriminant(*first))
+    {
+        return None;
+    }
+    let values = literals
+        .iter()
+        .map(|literal| literal.integer_value())
+        .collect::<Option<BTreeSet<u64>>>()?;
+    let (low, high) = (*values.iter().next()?, *values.iter().next_back()?);
+    if values.len() < MIN_SWITCH_CASES || high - low >= 2 * values.len() as u64 {
+        return None;
+    }
+    Some(SwitchCases { cases, has_default })
+}
+
+// the literals `scrutinee` matches, if it matches nothing else
+fn integer_literals<'sc>(scrutinee: &Scrutinee<'sc>) -> Option<Vec<Literal<'sc>>> {
+    match scrutinee {
+        Scrutinee::Literal { value, .. } => Some(vec![value.clone()]),
+        Scrutinee::Or { alternatives, .. } => {
+            let mut literals = vec![];
+            for alternative in alternatives.iter() {
+                literals.extend(integer_literals(alternative)?);
+            }
+            Some(literals)
+        }
+        _ => None,
+    }
+}
+
+// one block per arm with cases, with each integer sent to the block of the
+// first arm that tests for it
+fn lower_switch<'sc>(
+    primary: Expression<'sc>,
+    switch_cases: SwitchCases<'sc>,
+    arms: Vec<Arm<'sc>>,
+    span: Span<'sc>,
+) -> Node<'sc> {
+    let SwitchCases { cases, has_default } = switch_cases;
+    let low = cases
+        .iter()
+        .flatten()
+        .min_by_key(|literal| literal.integer_value())
+        .expect("a switch has cases")
+        .clone();
+    let offset = |literal: &Literal| {
+        let value = literal.integer_value().expect("switch cases are integers");
+        (value - low.integer_value().expect("switch cases are integers")) as usize
+    };
+    let size = cases.iter().flatten().map(offset).max().unwrap_or(0) + 1;
+
+    let mut table = vec![None; size];
+    for (index, literals) in cases.iter().enumerate() {
+        for literal in literals.iter() {
+            table[offset(literal)].get_or_insert(index);
+        }
+    }
+    let blocks = arms[..cases.len()].iter().map(Arm::body).collect();
+    let default = if has_default {
+        Some(arms[cases.len()].body())
+    } else {
+        None
+    };
+    Node::Switch(Switch {
+        primary,
+        low,
+        table,
+        blocks,
+        default,
+        span,
+    })
+}
+
+// a pattern that binds a name twice would have the second binding silently
+// shadow the first
+fn check_duplicate_bindings<'sc>(scrutinee: &Scrutinee<'sc>) -> Result<(), DesugarError<'sc>> {
+    let bindings = scrutinee.bindings();
+    for (i, name) in bindings.iter().enumerate() {
+        if bindings[..i]
+            .iter()
+            .any(|earlier| earlier.name() == name.name())
+        {
+            return Err(DesugarError::DuplicateBinding {
+                name: (*name).clone(),
+            });
+        }
+    }
+    Ok(())
+}
+
+fn check_duplicate_fields<'sc>(scrutinee: &Scrutinee<'sc>) -> Result<(), DesugarError<'sc>> {
+    match scrutinee {
+        Scrutinee::Literal { .. }
+        | Scrutinee::Range { .. }
+        | Scrutinee::VariableExpression { .. }
+        | Scrutinee::Wildcard { .. }
+        | Scrutinee::Rest { .. } => Ok(()),
+        Scrutinee::Tuple { elems, .. }
+        | Scrutinee::Array { elems, .. }
+        | Scrutinee::EnumVariant { payload: elems, .. }
+        | Scrutinee::Or {
+            alternatives: elems,
+            ..
+        } => elems.iter().try_for_each(check_duplicate_fields),
+        Scrutinee::Binding { sub, .. } => check_duplicate_fields(sub),
+        Scrutinee::StructScrutinee { fields, .. } => {
+            for (i, field) in fields.iter().enumerate() {
+                if fields[..i]
+                    .iter()
+                    .any(|earlier| earlier.field_name == field.field_name)
+                {
+                    return Err(DesugarError::DuplicateField {
+                        field: field.field_name.clone(),
+                    });
+                }
+                check_duplicate_fields(&field.scrutinee)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+// every type a pattern names has to be declared, with the shape the pattern
+// gives it
+fn check_declarations<'sc>(
+    scrutinee: &Scrutinee<'sc>,
+    namespace: &Namespace<'sc>,
+) -> Result<(), DesugarError<'sc>> {
+    match scrutinee {
+        Scrutinee::Literal { .. }
+        | Scrutinee::Range { .. }
+        | Scrutinee::VariableExpression { .. }
+        | Scrutinee::Wildcard { .. }
+        | Scrutinee::Rest { .. } => Ok(()),
+        Scrutinee::Tuple { elems, .. }
+        | Scrutinee::Array { elems, .. }
+        | Scrutinee::Or {
+            alternatives: elems,
+            ..
+        } => elems
+            .iter()
+            .try_for_each(|elem| check_declarations(elem, namespace)),
+        Scrutinee::Binding { sub, .. } => check_declarations(sub, namespace),
+        Scrutinee::StructScrutinee {
+            struct_name,
+            fields,
+            rest,
+            span,
+        } => {
+            let declaration = match namespace.get_struct(struct_name.primary_name) {
+                Some(declaration) => declaration,
+                None => {
+                    return Err(DesugarError::UnknownType {
+                        name: struct_name.clone(),
+                    })
+                }
+            };
+            for field in fields.iter() {
+                if declaration.field(field.field_name.primary_name).is_none() {
+                    return Err(DesugarError::UnknownField {
+                        struct_name: struct_name.primary_name,
+                        field: field.field_name.clone(),
+                    });
+                }
+                check_declarations(&field.scrutinee, namespace)?;
+            }
+            // without a `..` every field has to be spelled out
+            let missing = declaration.fields.iter().find(|declared| {
+                !fields
+                    .iter()
+                    .any(|field| field.field_name.primary_name == declared.name.primary_name)
+            });
+            match (missing, rest) {
+                (Some(missing), false) => Err(DesugarError::MissingField {
+                    struct_name: struct_name.primary_name,
+                    field: missing.name.primary_name,
+                    span: *span,
+                }),
+                _ => Ok(()),
+            }
+        }
+        Scrutinee::EnumVariant {
+            enum_name,
+            variant_name,
+            payload,
+            ..
+        } => {
+            let declaration = match namespace.get_enum(enum_name.primary_name) {
+                Some(declaration) => declaration,
+                None => {
+                    return Err(DesugarError::UnknownType {
+                        name: enum_name.clone(),
+                    })
+                }
+            };
+            let variant = match declaration.variant(variant_name.primary_name) {
+                Some(variant) => variant,
+                None => {
+                    return Err(DesugarError::UnknownVariant {
+                        enum_name: enum_name.primary_name,
+                        variant: variant_name.clone(),
+                    })
+                }
+            };
+            if variant.payload.len() != payload.len() {
+                return Err(DesugarError::PayloadArityMismatch {
+                    variant: variant_name.clone(),
+                    expected: variant.payload.len(),
+                    found: payload.len(),
+                });
+            }
+            payload
+                .iter()
+                .try_for_each(|elem| check_declarations(elem, namespace))
+        }
+    }
+}
+
+// a struct expression has to give each declared field exactly once
+fn check_struct_expression<'sc>(
+    struct_name: &Ident<'sc>,
+    fields: &[StructExpressionField<'sc>],
+    span: Span<'sc>,
+    namespace: &Namespace<'sc>,
+) -> Result<(), DesugarError<'sc>> {
+    let declaration = match namespace.get_struct(struct_name.primary_name) {
+        Some(declaration) => declaration,
+        None => {
+            return Err(DesugarError::UnknownType {
+                name: struct_name.clone(),
+            })
+        }
+    };
+    for (i, field) in fields.iter().enumerate() {
+        if declaration.field(field.name.primary_name).is_none() {
+            return Err(DesugarError::UnknownField {
+                struct_name: struct_name.primary_name,
+                field: field.name.clone(),
+            });
+        }
+        if fields[..i]
+            .iter()
+            .any(|earlier| earlier.name.primary_name == field.name.primary_name)
+        {
+            return Err(DesugarError::DuplicateFieldValue {
+                field: field.name.clone(),
+            });
+        }
+    }
+    let missing = declaration.fields.iter().find(|declared| {
+        !fields
+            .iter()
+            .any(|field| field.name.primary_name == declared.name.primary_name)
+    });
+    match missing {
+        Some(missing) => Err(DesugarError::MissingFieldValue {
+            struct_name: struct_name.primary_name,
+            field: missing.name.primary_name,
+            span,
+        }),
+        None => Ok(()),
+    }
+}
+
+// whether `exp` mentions the variable `name` anywhere
+fn refers_to(exp: &Expression, name: &str) -> bool {
+    match exp {
+        Expression::VariableExpression { name: other, .. } => other.name() == name,
+        Expression::Literal { .. } | Expression::Unit { .. } => false,
+        Expression::BinOp { left, right, .. } => refers_to(left, name) || refers_to(right, name),
+        Expression::Array {
+            contents: elems, ..
+        }
+        | Expression::Tuple { elems, .. } => elems.iter().any(|elem| refers_to(elem, name)),
+        Expression::StructExpression { fields, .. } => {
+            fields.iter().any(|field| refers_to(&field.value, name))
+        }
+        Expression::EnumInstantiation { payload, .. } => {
+            payload.iter().any(|elem| refers_to(elem, name))
+        }
+        Expression::TupleIndex { prefix, .. }
+        | Expression::ArrayIndex { prefix, .. }
+        | Expression::SubfieldExpression { prefix, .. }
+        | Expression::IndexFromEnd { prefix, .. }
+        | Expression::LengthCheck { prefix, .. }
+        | Expression::StructKindCheck { prefix, .. }
+        | Expression::VariantCheck { prefix, .. }
+        | Expression::VariantPayload { prefix, .. } => refers_to(prefix, name),
+        Expression::IfExp {
+            condition,
+            then,
+            r#else,
+            ..
+        } => {
+            refers_to(condition, name)
+                || refers_to(then, name)
+                || r#else
+                    .as_ref()
+                    .map_or(false, |r#else| refers_to(r#else, name))
+        }
+        // blocks and arms can introduce their own names, so play it safe
+        Expression::CodeBlock { .. } | Expression::Match { .. } => true,
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{desugar::desugar, language::constructors::*};
+    use crate::{
+        desugar::{desugar, desugar_tree, desugar_tree_with, Lowering},
+        error::{DesugarError, DesugarWarning, MatchError},
+        interpreter::interpret,
+        language::{constructors::*, Declaration, Expression, IfExpression, Namespace, Node},
+        parser::parse,
+        span::Span,
+        value::Value,
+    };
+
+    use std::convert::TryInto;
+
+    // a namespace in which `struct Point { x: u32, y: u32 }` is declared
+    fn point_namespace<'sc>() -> Namespace<'sc> {
+        let mut namespace = Namespace::default();
+        if let Node::Declaration(Declaration::StructDeclaration(declaration)) =
+            struct_declaration("Point", vec![("x", "u32"), ("y", "u32")])
+        {
+            namespace.declare_struct(declaration);
+        }
+        namespace
+    }
 
-    use std::collections::HashMap;
+    // desugars the `match` that ends `src` on its own, without knowing any of
+    // the values before it, and runs the program with it in its place
+    fn desugar_and_run(src: &str) -> (Result<Value<'_>, String>, Vec<DesugarWarning<'_>>) {
+        let mut nodes = parse(src).unwrap().nodes;
+        let namespace = Namespace::default();
+        let (desugared_node, warnings) = desugar(nodes.pop().unwrap(), &namespace).unwrap();
+        nodes.push(desugared_node);
+        (interpret(tree(nodes)), warnings)
+    }
 
     #[test]
     fn match_simple() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let node = match_(
             literal(u32_(5)),
             vec![
@@ -169,13 +1005,14 @@ mod test {
             ])),
         );
         let desugared = desugar(node, &namespace);
-        let desugared_node = desugared.unwrap();
+        let (desugared_node, warnings) = desugared.unwrap();
         assert_eq!(desugared_node, oracle_node);
+        assert!(warnings.is_empty());
     }
 
     #[test]
     fn match_struct() {
-        let mut namespace = HashMap::new();
+        let mut namespace = point_namespace();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -184,7 +1021,9 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let node = match_(
             variable("foo"),
@@ -193,8 +1032,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(literal_scrutinee(u32_(7))),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
                         ],
                     )),
                     variable("x"),
@@ -203,8 +1042,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(variable_scrutinee("y")),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", variable_scrutinee("y")),
                         ],
                     )),
                     variable("y"),
@@ -224,13 +1063,14 @@ mod test {
             ])),
         );
         let desugared = desugar(node, &namespace);
-        let desugared_node = desugared.unwrap();
+        let (desugared_node, warnings) = desugared.unwrap();
         assert_eq!(desugared_node, oracle_node);
+        assert!(warnings.is_empty());
     }
 
     #[test]
     fn match_struct_with_scrutinee() {
-        let mut namespace = HashMap::new();
+        let mut namespace = point_namespace();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -239,7 +1079,9 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let node = match_(
             variable("foo"),
@@ -248,8 +1090,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(literal_scrutinee(u32_(7))),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
                         ],
                     )),
                     variable("x"),
@@ -258,8 +1100,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(variable_scrutinee("y")),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", variable_scrutinee("y")),
                         ],
                     )),
                     variable("y"),
@@ -285,13 +1127,19 @@ mod test {
             )),
         );
         let desugared = desugar(node, &namespace);
-        let desugared_node = desugared.unwrap();
+        let (desugared_node, warnings) = desugared.unwrap();
         assert_eq!(desugared_node, oracle_node);
+        assert_eq!(
+            warnings,
+            vec![DesugarWarning::UnreachableArm {
+                span: Span::dummy()
+            }]
+        );
     }
 
     #[test]
     fn match_struct_multiple() {
-        let mut namespace = HashMap::new();
+        let mut namespace = point_namespace();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -300,7 +1148,9 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let node = match_(
             variable("foo"),
@@ -309,8 +1159,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(literal_scrutinee(u32_(0))),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", literal_scrutinee(u32_(0))),
                         ],
                     )),
                     variable("x"),
@@ -319,8 +1169,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(literal_scrutinee(u32_(0))),
-                            struct_scrutinee_field(variable_scrutinee("y")),
+                            struct_scrutinee_field("x", literal_scrutinee(u32_(0))),
+                            struct_scrutinee_field("y", variable_scrutinee("y")),
                         ],
                     )),
                     variable("y"),
@@ -329,8 +1179,8 @@ mod test {
                     match_scrutinee(struct_scrutinee(
                         "Point",
                         vec![
-                            struct_scrutinee_field(variable_scrutinee("x")),
-                            struct_scrutinee_field(variable_scrutinee("y")),
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", variable_scrutinee("y")),
                         ],
                     )),
                     variable("y"),
@@ -358,7 +1208,603 @@ mod test {
             )),
         );
         let desugared = desugar(node, &namespace);
-        let desugared_node = desugared.unwrap();
+        let (desugared_node, warnings) = desugared.unwrap();
         assert_eq!(desugared_node, oracle_node);
+        assert!(matches!(
+            warnings[..],
+            [DesugarWarning::PartiallyShadowedArm { .. }]
+        ));
+    }
+
+    #[test]
+    fn match_symbolic() {
+        // `pair` isn't known until runtime, as if it were a parameter
+        let namespace = Namespace::default();
+        let node = match_(
+            variable("pair"),
+            vec![
+                match_branch(
+                    match_scrutinee(tuple_scrutinee(vec![
+                        variable_scrutinee("x"),
+                        literal_scrutinee(u32_(5)),
+                    ])),
+                    variable("x"),
+                ),
+                match_branch(match_scrutinee_catchall(), literal(u32_(0))),
+            ],
+        );
+        // it's evaluated once, into a variable no user code can refer to
+        let scrutinee = || generated_variable("scrutinee", 0);
+        let oracle_node = expression(block(vec![
+            generated_declaration("scrutinee", 0, variable("pair")),
+            if_statement(
+                binop_and(
+                    length_check(scrutinee(), 2, false),
+                    binop_eq(literal(u32_(5)), tuple_index(scrutinee(), 1)),
+                ),
+                block(vec![
+                    variable_declaraction("x", tuple_index(scrutinee(), 0), false),
+                    expression(variable("x")),
+                ]),
+                Some(block(vec![expression(literal(u32_(0)))])),
+            ),
+        ]));
+        let (desugared_node, _) = desugar(node, &namespace).unwrap();
+        assert_eq!(desugared_node, oracle_node);
+
+        let program = tree(vec![
+            variable_declaraction(
+                "pair",
+                tuple(vec![literal(u32_(3)), literal(u32_(5))]),
+                false,
+            ),
+            desugared_node,
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(3)));
+    }
+
+    #[test]
+    fn match_scrutinee_evaluated_once() {
+        // the scrutinee gives a different value the second time around
+        let src = "
+            let mut seen = false;
+            match { if seen { (0, 0) } else { seen = true; (1, 5) } } {
+                (x, 5) => x,
+                _ => 2,
+            }
+        ";
+        assert_eq!(desugar_and_run(src).0, Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn match_or_patterns() {
+        let src = "
+            let pair = { (0, 7) };
+            match pair {
+                (x, 0) | (0, x) => x,
+                (1 | 2, _) => 1,
+                _ => 2,
+            }
+        ";
+        let (result, warnings) = desugar_and_run(src);
+        assert_eq!(result, Ok(Value::U32(7)));
+        // `(1, 0)` is taken by the first arm
+        assert!(matches!(
+            warnings.as_slice(),
+            [DesugarWarning::PartiallyShadowedArm { .. }]
+        ));
+    }
+
+    #[test]
+    fn match_guards() {
+        let src = "
+            let limit = 3;
+            let pair = { (3, 4) };
+            match pair {
+                (x, 4) if x == 4 => 1,
+                (x, y) if x == limit => y,
+                _ if true => 5,
+                _ => 6,
+            }
+        ";
+        let (result, warnings) = desugar_and_run(src);
+        assert_eq!(result, Ok(Value::U32(4)));
+        // guarded arms can't shadow anything
+        assert_eq!(warnings, vec![]);
+    }
+
+    #[test]
+    fn match_binding_patterns() {
+        let src = "
+            let pair = { (3, (4, 5)) };
+            match pair {
+                (x, inner @ (4, y)) => (inner, y),
+                (_, inner) => (inner, 0),
+            }
+        ";
+        let inner = Value::Tuple(vec![Value::U32(4), Value::U32(5)]);
+        assert_eq!(
+            desugar_and_run(src).0,
+            Ok(Value::Tuple(vec![inner, Value::U32(5)]))
+        );
+
+        let node = parse("match pair { x @ (x, _) => x }")
+            .unwrap()
+            .nodes
+            .remove(0);
+        assert!(matches!(
+            desugar(node, &Namespace::default()),
+            Err(DesugarError::DuplicateBinding { .. })
+        ));
+    }
+
+    #[test]
+    fn match_ranges() {
+        let src = "
+            let digit = { 7 };
+            match (digit, 300) {
+                (0..=4, _) => 0,
+                (5..10, ..=255) => 1,
+                (5..10, _) => 2,
+                (10.., _) => 3,
+            }
+        ";
+        let (result, warnings) = desugar_and_run(src);
+        assert_eq!(result, Ok(Value::U32(2)));
+        // small digits are taken by the arm before
+        assert!(matches!(
+            warnings.as_slice(),
+            [DesugarWarning::PartiallyShadowedArm { .. }]
+        ));
+    }
+
+    #[test]
+    fn match_arrays() {
+        let src = "
+            let xs = { [1, 2, 3] };
+            match xs {
+                [] => 0,
+                [x] => x,
+                [first, .., 3] => first,
+                [_, ..] => 20,
+            }
+        ";
+        assert_eq!(desugar_and_run(src).0, Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn match_hygiene() {
+        // a user variable that looks like the generated one is left alone
+        let src = "
+            let pair = 2;
+            let scrutinee = 1;
+            match (scrutinee, pair) {
+                (x, scrutinee) => (x, scrutinee),
+            }
+        ";
+        assert_eq!(
+            desugar_and_run(src).0,
+            Ok(Value::Tuple(vec![Value::U32(1), Value::U32(2)]))
+        );
+    }
+
+    #[test]
+    fn match_duplicate_binding() {
+        let src = "match pair {\n    (x, x) => x,\n}";
+        let node = parse(src).unwrap().nodes.remove(0);
+        let namespace = Namespace::default();
+        let err = desugar(node, &namespace).unwrap_err();
+        assert!(matches!(err, DesugarError::DuplicateBinding { .. }));
+        assert_eq!(err.span().line_col(), (2, 9));
+    }
+
+    #[test]
+    fn match_duplicate_field() {
+        let src = "match foo {\n    Point { x, y: 1, x: z } => z,\n    _ => 0,\n}";
+        let node = parse(src).unwrap().nodes.remove(0);
+        let namespace = Namespace::default();
+        let err = desugar(node, &namespace).unwrap_err();
+        assert!(matches!(err, DesugarError::DuplicateField { .. }));
+        assert_eq!(err.span().as_str(), "x");
+        assert_eq!(err.span().line_col(), (2, 22));
+    }
+
+    #[test]
+    fn match_unreachable_spans() {
+        let src = "match 5 {\n    x => 1,\n    5 => 2,\n}";
+        let node = parse(src).unwrap().nodes.remove(0);
+        let namespace = Namespace::default();
+        let (_, warnings) = desugar(node, &namespace).unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].span().as_str(), "5 => 2");
+        assert_eq!(warnings[0].to_string(), "Unreachable match arm");
+    }
+
+    #[test]
+    fn match_spans() {
+        let src = "match 5 {\n    5 => 42,\n    foo => foo,\n}";
+        let node = parse(src).unwrap().nodes.remove(0);
+        let namespace = Namespace::default();
+        let (desugared, _) = desugar(node, &namespace).unwrap();
+        let (primary, right, span) = match desugared {
+            Node::IfExpression(IfExpression {
+                primary,
+                right: Some(right),
+                span,
+                ..
+            }) => (primary, right, span),
+            node => panic!("expected an if expression, found {}", node),
+        };
+        assert_eq!(span.as_str(), "5 => 42");
+        assert_eq!(primary.span().as_str(), "5 => 42");
+        let declaration_span = match right {
+            Expression::CodeBlock { contents, .. } => contents.contents[0].span(),
+            exp => panic!("expected a code block, found {}", exp),
+        };
+        assert_eq!(declaration_span.as_str(), "foo => foo");
+    }
+
+    #[test]
+    fn match_incompatible() {
+        let namespace = Namespace::default();
+        let node = match_(
+            literal(u32_(5)),
+            vec![match_branch(
+                match_scrutinee(tuple_scrutinee(vec![variable_scrutinee("x")])),
+                variable("x"),
+            )],
+        );
+        let desugared = desugar(node, &namespace);
+        assert!(matches!(
+            desugared,
+            Err(DesugarError::Match(MatchError::IncompatiblePattern { .. }))
+        ));
+    }
+
+    #[test]
+    fn match_switch() {
+        let namespace = Namespace::default();
+        let case = |n| {
+            match_branch(
+                match_scrutinee(literal_scrutinee(u32_(n))),
+                literal(u32_(n)),
+            )
+        };
+        let default = || match_branch(match_scrutinee(variable_scrutinee("n")), variable("n"));
+        let node = match_(
+            variable("opcode"),
+            vec![
+                case(1),
+                match_branch(
+                    match_scrutinee(or_scrutinee(vec![
+                        literal_scrutinee(u32_(2)),
+                        literal_scrutinee(u32_(3)),
+                        literal_scrutinee(u32_(1)),
+                    ])),
+                    literal(u32_(0)),
+                ),
+                case(4),
+                case(6),
+                default(),
+            ],
+        );
+        let scrutinee = || generated_variable("scrutinee", 0);
+        let oracle_node = expression(block(vec![
+            generated_declaration("scrutinee", 0, variable("opcode")),
+            switch(
+                scrutinee(),
+                u32_(1),
+                // `1` goes to the first arm that tests for it
+                vec![Some(0), Some(1), Some(1), Some(2), None, Some(3)],
+                vec![
+                    block(vec![expression(literal(u32_(1)))]),
+                    block(vec![expression(literal(u32_(0)))]),
+                    block(vec![expression(literal(u32_(4)))]),
+                    block(vec![expression(literal(u32_(6)))]),
+                ],
+                Some(block(vec![
+                    variable_declaraction("n", scrutinee(), false),
+                    expression(variable("n")),
+                ])),
+            ),
+        ]));
+        let (desugared_node, _) = desugar(node, &namespace).unwrap();
+        assert_eq!(desugared_node, oracle_node);
+        for (opcode, result) in [(3, 0), (5, 5), (6, 6), (7, 7), (0, 0)] {
+            let program = tree(vec![
+                variable_declaraction("opcode", literal(u32_(opcode)), false),
+                desugared_node.clone(),
+            ]);
+            assert_eq!(interpret(program), Ok(Value::U32(result)));
+        }
+
+        // too few cases, or cases too far apart, are left as a chain of tests
+        for cases in [vec![1, 2, 3], vec![1, 2, 3, 100]] {
+            let mut branches: Vec<_> = cases.into_iter().map(case).collect();
+            branches.push(default());
+            let node = match_(variable("opcode"), branches);
+            let (desugared_node, _) = desugar(node, &namespace).unwrap();
+            match desugared_node {
+                Node::Expression(Expression::CodeBlock { contents, .. }) => {
+                    assert!(matches!(contents.contents[1], Node::IfExpression(_)))
+                }
+                node => panic!("expected a block, found {}", node),
+            }
+        }
+    }
+
+    #[test]
+    fn match_as_expression() {
+        let namespace = Namespace::default();
+        let node = variable_declaraction(
+            "y",
+            match_expression(
+                variable("x"),
+                vec![
+                    match_branch(
+                        match_scrutinee(literal_scrutinee(boolean(true))),
+                        literal(u32_(1)),
+                    ),
+                    match_branch(
+                        match_scrutinee(literal_scrutinee(boolean(false))),
+                        literal(u32_(0)),
+                    ),
+                ],
+            ),
+            false,
+        );
+        let scrutinee = || generated_variable("scrutinee", 0);
+        let oracle_node = variable_declaraction(
+            "y",
+            block(vec![
+                generated_declaration("scrutinee", 0, variable("x")),
+                if_statement(
+                    binop_eq(literal(boolean(true)), scrutinee()),
+                    block(vec![expression(literal(u32_(1)))]),
+                    Some(if_expression(
+                        binop_eq(literal(boolean(false)), scrutinee()),
+                        block(vec![expression(literal(u32_(0)))]),
+                        None,
+                    )),
+                ),
+            ]),
+            false,
+        );
+        let (desugared_node, _) = desugar(node, &namespace).unwrap();
+        assert_eq!(desugared_node, oracle_node);
+
+        // a match in the arm of another is desugared too, and neither takes
+        // the value of `x` from the namespace, since an arm can shadow it
+        let src = "
+            let pair = (match x { 0 => (false, 0), x => (true, match x { 1 => 10, _ => 20 }) }, 5);
+        ";
+        let mut namespace = Namespace::default();
+        namespace.insert("x".to_string(), Value::U32(0));
+        let node = parse(src).unwrap().nodes.pop().unwrap();
+        let (desugared_node, _) = desugar(node, &namespace).unwrap();
+        assert!(!desugared_node.to_string().contains("Match"));
+        for (x, result) in [(0, (false, 0)), (1, (true, 10)), (2, (true, 20))] {
+            let program = tree(vec![
+                variable_declaraction("x", literal(u32_(x)), false),
+                desugared_node.clone(),
+                expression(tuple_index(variable("pair"), 0)),
+            ]);
+            let result = Value::Tuple(vec![Value::Boolean(result.0), Value::U32(result.1)]);
+            assert_eq!(interpret(program), Ok(result));
+        }
+    }
+
+    #[test]
+    fn desugar_nested_matches() {
+        let src = "
+            struct Point { x: u32, y: u32 }
+            let p = Point { x: 1, y: 2 };
+            let mut seen = false;
+            while match seen { false => true, true => false } {
+                seen = true;
+            }
+            let r = if seen {
+                match p {
+                    Point { x: 1, y } => match y { 2 => { match (y, p.x) { (2, x) => x, _ => 3 } } _ => 4 },
+                    _ => 5,
+                }
+            } else {
+                6
+            };
+            match r {
+                1 => Point { x: r, y: 0 },
+                _ => p,
+            }
+        ";
+        let expected = Value::Struct {
+            struct_name: "Point",
+            fields: vec![("x", Value::U32(1)), ("y", Value::U32(0))],
+        };
+        assert_eq!(interpret(parse(src).unwrap()), Ok(expected.clone()));
+        for lowering in [Lowering::Chain, Lowering::DecisionTree] {
+            let (tree, warnings) = desugar_tree_with(parse(src).unwrap(), lowering).unwrap();
+            assert!(!tree.to_string().contains("Match"));
+            assert!(warnings.is_empty());
+            assert_eq!(interpret(tree), Ok(expected.clone()));
+        }
+
+        // errors in nested matches are reported too
+        let tree = parse("let x = { if true { match 1 { 0 => 0 } } else { 1 } };").unwrap();
+        assert!(matches!(
+            desugar_tree(tree),
+            Err(DesugarError::NonExhaustive { .. })
+        ));
+    }
+
+    #[test]
+    fn desugar_destructuring_declarations() {
+        let pattern = || {
+            tuple_scrutinee(vec![
+                variable_scrutinee("a"),
+                tuple_scrutinee(vec![wildcard_scrutinee(), variable_scrutinee("b")]),
+            ])
+        };
+        let program = tree(vec![
+            variable_declaraction("a", literal(u32_(0)), false),
+            expression(block(vec![
+                destructuring_declaration(pattern(), variable("pair"), true),
+                expression(variable("b")),
+            ])),
+        ]);
+        // the body is evaluated once, and each binding is declared in the
+        // block of the `let`
+        let scrutinee = || generated_variable("scrutinee", 0);
+        let oracle = vec![
+            variable_declaraction("a", literal(u32_(0)), false),
+            expression(block(vec![
+                generated_declaration("scrutinee", 0, variable("pair")),
+                variable_declaraction("a", tuple_index(scrutinee(), 0), true),
+                variable_declaraction("b", tuple_index(tuple_index(scrutinee(), 1), 1), true),
+                expression(variable("b")),
+            ])),
+        ];
+        let (desugared, warnings) = desugar_tree(program).unwrap();
+        assert_eq!(desugared.nodes, oracle);
+        assert!(warnings.is_empty());
+
+        let src = "
+            struct Point { x: u32, y: u32 }
+            let Point { x, y: z } = Point { x: 1, y: 2 };
+            let (a, (b, _)) = (z, (x, 3));
+            (a, b)
+        ";
+        let (desugared, _) = desugar_tree(parse(src).unwrap()).unwrap();
+        assert_eq!(
+            interpret(desugared),
+            Ok(Value::Tuple(vec![Value::U32(2), Value::U32(1)]))
+        );
+
+        let program = tree(vec![destructuring_declaration(
+            tuple_scrutinee(vec![variable_scrutinee("a"), literal_scrutinee(u32_(1))]),
+            variable("pair"),
+            false,
+        )]);
+        match desugar_tree(program) {
+            Err(err @ DesugarError::RefutablePattern { .. }) => assert_eq!(
+                err.to_string(),
+                "Refutable pattern in let, pattern (_, 0) is not covered"
+            ),
+            result => panic!("expected a refutable pattern, found {:?}", result),
+        }
+        let program = tree(vec![destructuring_declaration(
+            struct_scrutinee("Point", vec![]),
+            variable("p"),
+            false,
+        )]);
+        assert!(matches!(
+            desugar_tree(program),
+            Err(DesugarError::UnknownType { .. })
+        ));
+    }
+
+    #[test]
+    fn match_empty() {
+        let namespace = Namespace::default();
+        let node = match_(literal(u32_(5)), vec![]);
+        let desugared = desugar(node, &namespace);
+        assert!(matches!(desugared, Err(DesugarError::EmptyMatch { .. })));
+    }
+
+    #[test]
+    fn match_non_exhaustive() {
+        let namespace = Namespace::default();
+        let node = match_(
+            literal(boolean(true)),
+            vec![match_branch(
+                match_scrutinee(literal_scrutinee(boolean(true))),
+                literal(u32_(1)),
+            )],
+        );
+        let desugared = desugar(node, &namespace);
+        match desugared {
+            Err(DesugarError::NonExhaustive { witness, .. }) => {
+                assert_eq!(witness.to_string(), "false")
+            }
+            desugared => panic!("expected a non-exhaustive match, found {:?}", desugared),
+        }
+    }
+
+    #[test]
+    fn match_enums() {
+        let src = "
+            enum Shape { Dot, Circle(u32), Rect(u32, u32) }
+            let shape = Shape::Rect(2, 3);
+            match shape {
+                Shape::Dot => 0,
+                Shape::Circle(r) => r,
+                Shape::Rect(1, h) | Shape::Rect(h, 1) => h,
+                Shape::Rect(w, _) => w,
+            }
+        ";
+        assert_eq!(interpret(parse(src).unwrap()), Ok(Value::U32(2)));
+
+        let src = "enum Shape { Dot, Circle(u32) }
+match Shape::Dot {
+    Shape::Circle(_) => 1,
+}";
+        let err = desugar_tree(parse(src).unwrap()).unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Non-exhaustive match, pattern Shape::Dot is not covered"
+        );
+
+        let src = "enum Shape { Dot }
+match Shape::Dot {
+    Shape::Square => 1,
+}";
+        let err = interpret(parse(src).unwrap()).unwrap_err();
+        assert_eq!(err, "Enum Shape has no variant Square");
+    }
+
+    #[test]
+    fn match_struct_declarations() {
+        let namespace = point_namespace();
+        let desugar_src = |src| desugar(parse(src).unwrap().nodes.remove(0), &namespace);
+
+        assert!(desugar_src("match p {\n    Point { x, .. } => x,\n}").is_ok());
+
+        let err = desugar_src("match p {\n    Point { x } => x,\n}").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "Pattern does not mention field y of struct Point"
+        );
+        assert_eq!(err.span().line_col(), (2, 5));
+
+        let err = desugar_src("match p {\n    Point { x, z, .. } => x,\n}").unwrap_err();
+        assert!(matches!(err, DesugarError::UnknownField { .. }));
+        assert_eq!(err.span().as_str(), "z");
+
+        let err = desugar_src("match p {\n    Pointe { x, y } => x,\n}").unwrap_err();
+        assert!(matches!(err, DesugarError::UnknownType { .. }));
+        assert_eq!(err.span().as_str(), "Pointe");
+
+        // struct expressions are checked too, against the declarations in
+        // scope where they're written
+        let desugar_tree_src = |src| desugar_tree(parse(src).unwrap()).map(|_| ());
+        let err = desugar_tree_src(
+            "struct P { x: u32 }
+let p = P { x: 1, x: 2 };",
+        )
+        .unwrap_err();
+        assert!(matches!(err, DesugarError::DuplicateFieldValue { .. }));
+        assert_eq!(err.span().line_col(), (2, 19));
+
+        let err = desugar_tree_src(
+            "struct P { x: u32, y: u32 }
+let p = P { x: 1 };",
+        )
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Struct P is missing field y");
+
+        let src = "let p = { struct P { x: u32 } P { x: 1 } };
+let q = P { x: 2 };";
+        let err = desugar_tree_src(src).unwrap_err();
+        assert!(matches!(err, DesugarError::UnknownType { .. }));
+        assert_eq!(err.span().line_col(), (2, 9));
     }
 }
@@ -0,0 +1,314 @@
+use std::fmt;
+
+use crate::language::*;
+use crate::span::Span;
+use crate::usefulness::Pattern;
+
+/// Why a scrutinee could not be matched against an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchError<'sc> {
+    /// The scrutinee and the expression have different shapes, e.g. a tuple
+    /// pattern against a literal.
+    IncompatiblePattern {
+        scrutinee: Box<Scrutinee<'sc>>,
+        exp: Box<Expression<'sc>>,
+    },
+    TupleArityMismatch {
+        scrutinee: Box<Scrutinee<'sc>>,
+        exp: Box<Expression<'sc>>,
+        expected: usize,
+        found: usize,
+    },
+    UnknownField {
+        scrutinee: Box<Scrutinee<'sc>>,
+        exp: Box<Expression<'sc>>,
+        field: Ident<'sc>,
+    },
+    /// A struct pattern without `..` that leaves out one of the fields.
+    MissingField {
+        scrutinee: Box<Scrutinee<'sc>>,
+        exp: Box<Expression<'sc>>,
+        field: &'sc str,
+    },
+    /// A `..` anywhere but directly inside a tuple or array pattern, or a
+    /// second one in the same pattern.
+    MisplacedRest {
+        span: Span<'sc>,
+    },
+    /// An alternative of an or-pattern, at `span`, that doesn't bind `name`
+    /// even though another alternative does.
+    InconsistentOrBindings {
+        name: Ident<'sc>,
+        span: Span<'sc>,
+    },
+    UnboundVariable {
+        name: Ident<'sc>,
+    },
+    /// The expression can't be evaluated by the matcher.
+    UnsupportedExpression {
+        exp: Box<Expression<'sc>>,
+    },
+}
+
+impl<'sc> MatchError<'sc> {
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            MatchError::IncompatiblePattern { scrutinee, .. }
+            | MatchError::TupleArityMismatch { scrutinee, .. }
+            | MatchError::MissingField { scrutinee, .. } => scrutinee.span(),
+            MatchError::MisplacedRest { span }
+            | MatchError::InconsistentOrBindings { span, .. } => *span,
+            MatchError::UnknownField { field, .. } => field.span,
+            MatchError::UnboundVariable { name } => name.span,
+            MatchError::UnsupportedExpression { exp } => exp.span(),
+        }
+    }
+}
+
+impl<'sc> fmt::Display for MatchError<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MatchError::IncompatiblePattern { scrutinee, exp } => {
+                write!(f, "Pattern {} is incompatible with {}", scrutinee, exp)
+            }
+            MatchError::TupleArityMismatch {
+                scrutinee,
+                expected,
+                found,
+                ..
+            } => write!(
+                f,
+                "Pattern {} has {} elements but the tuple has {}",
+                scrutinee, expected, found
+            ),
+            MatchError::UnknownField {
+                scrutinee, field, ..
+            } => write!(
+                f,
+                "Pattern {} refers to unknown field {}",
+                scrutinee, field.primary_name
+            ),
+            MatchError::MissingField {
+                scrutinee, field, ..
+            } => write!(
+                f,
+                "Pattern {} doesn't mention field {}, use `..` to ignore it",
+                scrutinee, field
+            ),
+            MatchError::MisplacedRest { .. } => {
+                write!(
+                    f,
+                    "`..` can only be used once, directly inside a tuple or array pattern"
+                )
+            }
+            MatchError::InconsistentOrBindings { name, .. } => write!(
+                f,
+                "Variable {} is not bound in every alternative of the pattern",
+                name.name()
+            ),
+            MatchError::UnboundVariable { name } => {
+                write!(f, "Unbound variable {}", name.name())
+            }
+            MatchError::UnsupportedExpression { exp } => {
+                write!(f, "Can't match on expression {}", exp)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesugarError<'sc> {
+    Match(MatchError<'sc>),
+    /// A `match` without any arms.
+    EmptyMatch {
+        span: Span<'sc>,
+    },
+    /// A `match` that some value falls through, with an example of such a
+    /// value.
+    NonExhaustive {
+        witness: Box<Pattern<'sc>>,
+        span: Span<'sc>,
+    },
+    /// A `let` whose pattern doesn't match every value, with an example of
+    /// a value it doesn't match.
+    RefutablePattern {
+        witness: Box<Pattern<'sc>>,
+        span: Span<'sc>,
+    },
+    /// A pattern that binds the same variable twice, e.g. `(x, x)`.
+    DuplicateBinding {
+        name: Ident<'sc>,
+    },
+    /// A struct pattern that mentions the same field twice.
+    DuplicateField {
+        field: Ident<'sc>,
+    },
+    /// A pattern or struct expression naming a type that hasn't been
+    /// declared, or whose declaration is out of scope.
+    UnknownType {
+        name: Ident<'sc>,
+    },
+    /// An enum pattern naming a variant its enum doesn't have.
+    UnknownVariant {
+        enum_name: &'sc str,
+        variant: Ident<'sc>,
+    },
+    /// A struct pattern or expression naming a field its struct doesn't
+    /// declare.
+    UnknownField {
+        struct_name: &'sc str,
+        field: Ident<'sc>,
+    },
+    /// A struct pattern without `..` that leaves out one of the declared
+    /// fields.
+    MissingField {
+        struct_name: &'sc str,
+        field: &'sc str,
+        span: Span<'sc>,
+    },
+    /// A struct expression that gives the same field twice.
+    DuplicateFieldValue {
+        field: Ident<'sc>,
+    },
+    /// A struct expression that leaves out one of the declared fields.
+    MissingFieldValue {
+        struct_name: &'sc str,
+        field: &'sc str,
+        span: Span<'sc>,
+    },
+    /// An enum pattern with a different number of values than its variant
+    /// carries.
+    PayloadArityMismatch {
+        variant: Ident<'sc>,
+        expected: usize,
+        found: usize,
+    },
+}
+
+impl<'sc> DesugarError<'sc> {
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            DesugarError::Match(err) => err.span(),
+            DesugarError::EmptyMatch { span }
+            | DesugarError::NonExhaustive { span, .. }
+            | DesugarError::RefutablePattern { span, .. } => *span,
+            DesugarError::DuplicateBinding { name } => name.span,
+            DesugarError::DuplicateField { field }
+            | DesugarError::DuplicateFieldValue { field } => field.span,
+            DesugarError::UnknownType { name } => name.span,
+            DesugarError::UnknownField { field, .. } => field.span,
+            DesugarError::MissingField { span, .. }
+            | DesugarError::MissingFieldValue { span, .. } => *span,
+            DesugarError::UnknownVariant { variant, .. }
+            | DesugarError::PayloadArityMismatch { variant, .. } => variant.span,
+        }
+    }
+}
+
+impl<'sc> From<MatchError<'sc>> for DesugarError<'sc> {
+    fn from(err: MatchError<'sc>) -> Self {
+        DesugarError::Match(err)
+    }
+}
+
+impl<'sc> fmt::Display for DesugarError<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesugarError::Match(err) => write!(f, "{}", err),
+            DesugarError::EmptyMatch { .. } => write!(f, "Match statement has no arms"),
+            DesugarError::NonExhaustive { witness, .. } => {
+                write!(
+                    f,
+                    "Non-exhaustive match, pattern {} is not covered",
+                    witness
+                )
+            }
+            DesugarError::RefutablePattern { witness, .. } => write!(
+                f,
+                "Refutable pattern in let, pattern {} is not covered",
+                witness
+            ),
+            DesugarError::DuplicateBinding { name } => write!(
+                f,
+                "Variable {} is bound more than once in the same pattern",
+                name.name()
+            ),
+            DesugarError::DuplicateField { field } => write!(
+                f,
+                "Field {} is mentioned more than once in the same pattern",
+                field.name()
+            ),
+            DesugarError::UnknownType { name } => write!(f, "Unknown type {}", name.name()),
+            DesugarError::UnknownField { struct_name, field } => {
+                write!(f, "Struct {} has no field {}", struct_name, field.name())
+            }
+            DesugarError::MissingField {
+                struct_name, field, ..
+            } => write!(
+                f,
+                "Pattern does not mention field {} of struct {}",
+                field, struct_name
+            ),
+            DesugarError::DuplicateFieldValue { field } => {
+                write!(f, "Field {} is given more than once", field.name())
+            }
+            DesugarError::MissingFieldValue {
+                struct_name, field, ..
+            } => write!(f, "Struct {} is missing field {}", struct_name, field),
+            DesugarError::UnknownVariant { enum_name, variant } => {
+                write!(f, "Enum {} has no variant {}", enum_name, variant.name())
+            }
+            DesugarError::PayloadArityMismatch {
+                variant,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Variant {} carries {} values, but the pattern has {}",
+                variant.name(),
+                expected,
+                found
+            ),
+        }
+    }
+}
+
+/// Something suspicious about a `match` that doesn't stop it from being
+/// desugared.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DesugarWarning<'sc> {
+    /// An arm that no value can reach, because earlier arms match everything
+    /// it does.
+    UnreachableArm { span: Span<'sc> },
+    /// An arm that some, but not all, of its values never reach, because an
+    /// earlier arm takes them first.
+    PartiallyShadowedArm {
+        span: Span<'sc>,
+        shadowed_by: Span<'sc>,
+    },
+}
+
+impl<'sc> DesugarWarning<'sc> {
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            DesugarWarning::UnreachableArm { span }
+            | DesugarWarning::PartiallyShadowedArm { span, .. } => *span,
+        }
+    }
+}
+
+impl<'sc> fmt::Display for DesugarWarning<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DesugarWarning::UnreachableArm { .. } => write!(f, "Unreachable match arm"),
+            DesugarWarning::PartiallyShadowedArm { shadowed_by, .. } => {
+                let (line, col) = shadowed_by.line_col();
+                write!(
+                    f,
+                    "Match arm is partially shadowed by the arm at {}:{}",
+                    line, col
+                )
+            }
+        }
+    }
+}
@@ -1,5 +1,791 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::mem;
+
+use crate::desugar::{desugar_tree_with, Lowering};
 use crate::language::*;
+use crate::value::Value;
+
+/// Evaluates `tree` and returns the value of its final node, or the value
+/// handed to the first `return` that is reached.
+pub fn interpret<'sc>(tree: Tree<'sc>) -> Result<Value<'sc>, String> {
+    interpret_with(tree, Lowering::default())
+}
+
+/// Like `interpret`, with every `match` lowered as `lowering` says.
+pub fn interpret_with<'sc>(tree: Tree<'sc>, lowering: Lowering) -> Result<Value<'sc>, String> {
+    // every `match` is desugared once, up front, so that one in a loop costs
+    // no more than the `if`s or switch it became
+    match desugar_tree_with(tree, lowering) {
+        Ok((tree, _warnings)) => run(tree),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+// evaluates a tree that has already been desugared
+fn run(tree: Tree) -> Result<Value, String> {
+    let mut interpreter = Interpreter::new();
+    match interpreter.eval_nodes(&tree.nodes) {
+        Ok(value) => Ok(value),
+        Err(Interrupt::Return(value)) => Ok(value),
+        Err(Interrupt::Error(err)) => Err(err),
+    }
+}
+
+// Anything that stops evaluation from simply moving on to the next node.
+enum Interrupt<'sc> {
+    Return(Value<'sc>),
+    Error(String),
+}
+
+type Eval<'sc> = Result<Value<'sc>, Interrupt<'sc>>;
+
+fn error<'sc, T>(message: String) -> Result<T, Interrupt<'sc>> {
+    Err(Interrupt::Error(message))
+}
+
+// `interpret` desugars every `match` and destructuring `let` before anything
+// is evaluated
+fn undesugared<'sc>() -> Eval<'sc> {
+    error("Found a match or destructuring let that wasn't desugared".to_string())
+}
+
+// only integers of the same width are ordered
+fn compare_integers(left: &Value, right: &Value) -> Option<Ordering> {
+    match (left, right) {
+        (Value::U8(left), Value::U8(right)) | (Value::Byte(left), Value::Byte(right)) => {
+            Some(left.cmp(right))
+        }
+        (Value::U16(left), Value::U16(right)) => Some(left.cmp(right)),
+        (Value::U32(left), Value::U32(right)) => Some(left.cmp(right)),
+        (Value::U64(left), Value::U64(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+struct Binding<'sc> {
+    value: Value<'sc>,
+    is_mutable: bool,
+}
+
+// the variables and types declared in a block, which are visible until the
+// end of it
+#[derive(Default)]
+struct Scope<'sc> {
+    bindings: HashMap<String, Binding<'sc>>,
+    types: Namespace<'sc>,
+}
+
+struct Interpreter<'sc> {
+    scopes: Vec<Scope<'sc>>,
+}
+
+impl<'sc> Interpreter<'sc> {
+    fn new() -> Self {
+        Interpreter {
+            scopes: vec![Scope::default()],
+        }
+    }
+
+    fn lookup(&self, name: &str) -> Option<&Binding<'sc>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.bindings.get(name))
+    }
+
+    fn lookup_mut(&mut self, name: &str) -> Option<&mut Binding<'sc>> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .find_map(|scope| scope.bindings.get_mut(name))
+    }
+
+    fn lookup_struct(&self, name: &str) -> Option<&StructDeclaration<'sc>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.types.get_struct(name))
+    }
+
+    fn lookup_enum(&self, name: &str) -> Option<&EnumDeclaration<'sc>> {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.types.get_enum(name))
+    }
+
+    fn scope(&mut self) -> &mut Scope<'sc> {
+        self.scopes
+            .last_mut()
+            .expect("interpreter always has a global scope")
+    }
+
+    fn declare(&mut self, name: &str, value: Value<'sc>, is_mutable: bool) {
+        self.scope()
+            .bindings
+            .insert(name.to_string(), Binding { value, is_mutable });
+    }
+
+    fn eval_nodes(&mut self, nodes: &[Node<'sc>]) -> Eval<'sc> {
+        let mut value = Value::Unit;
+        for node in nodes.iter() {
+            value = self.eval_node(node)?;
+        }
+        Ok(value)
+    }
+
+    fn eval_scoped(&mut self, nodes: &[Node<'sc>]) -> Eval<'sc> {
+        self.scopes.push(Scope::default());
+        let value = self.eval_nodes(nodes);
+        self.scopes.pop();
+        value
+    }
+
+    fn eval_node(&mut self, node: &Node<'sc>) -> Eval<'sc> {
+        match node {
+            Node::Declaration(Declaration::VariableDeclaration(VariableDeclaration {
+                name,
+                body,
+                is_mutable,
+                ..
+            })) => {
+                let value = self.eval_exp(body)?;
+                self.declare(&name.name(), value, *is_mutable);
+                Ok(Value::Unit)
+            }
+            Node::Declaration(Declaration::Reassignment(Reassignment { lhs, rhs, .. })) => {
+                let name = match lhs.as_ref() {
+                    Expression::VariableExpression { name, .. } => name.name(),
+                    lhs => return error(format!("Cannot assign to {}", lhs)),
+                };
+                let value = self.eval_exp(rhs)?;
+                match self.lookup_mut(&name) {
+                    Some(binding) if binding.is_mutable => {
+                        binding.value = value;
+                        Ok(Value::Unit)
+                    }
+                    Some(_) => error(format!("Cannot reassign immutable variable {}", name)),
+                    None => error(format!("Unbound variable {}", name)),
+                }
+            }
+            Node::Declaration(Declaration::EnumDeclaration(enum_declaration)) => {
+                self.scope().types.declare_enum(enum_declaration.clone());
+                Ok(Value::Unit)
+            }
+            Node::Declaration(Declaration::StructDeclaration(struct_declaration)) => {
+                self.scope()
+                    .types
+                    .declare_struct(struct_declaration.clone());
+                Ok(Value::Unit)
+            }
+            Node::Expression(exp) => self.eval_exp(exp),
+            Node::WhileLoop(WhileLoop {
+                condition, body, ..
+            }) => {
+                while self.eval_bool(condition)? {
+                    self.eval_scoped(&body.contents)?;
+                }
+                Ok(Value::Unit)
+            }
+            Node::ReturnStatement(ReturnStatement { expr, .. }) => {
+                let value = self.eval_exp(expr)?;
+                Err(Interrupt::Return(value))
+            }
+            Node::IfExpression(IfExpression {
+                primary,
+                left,
+                right,
+                ..
+            }) => {
+                if self.eval_bool(primary)? {
+                    self.eval_exp(left)
+                } else {
+                    match right {
+                        Some(right) => self.eval_exp(right),
+                        None => Ok(Value::Unit),
+                    }
+                }
+            }
+            Node::Switch(Switch {
+                primary,
+                low,
+                table,
+                blocks,
+                default,
+                ..
+            }) => {
+                let value = self.eval_exp(primary)?;
+                // a value of another width is equal to none of the cases, just
+                // as it is to the literals in a chain of `==`s
+                let offset = match (value.integer_value(), low.integer_value()) {
+                    (Some(n), Some(low_n))
+                        if mem::discriminant(&value)
+                            == mem::discriminant(&Value::from(low.clone())) =>
+                    {
+                        n.checked_sub(low_n)
+                    }
+                    _ => None,
+                };
+                let block = offset
+                    .and_then(|offset| usize::try_from(offset).ok())
+                    .and_then(|offset| table.get(offset).copied().flatten())
+                    .map(|index| &blocks[index]);
+                match block.or(default.as_ref()) {
+                    Some(block) => self.eval_exp(block),
+                    None => Ok(Value::Unit),
+                }
+            }
+            Node::Declaration(Declaration::DestructuringDeclaration(_))
+            | Node::MatchStatement(_) => undesugared(),
+        }
+    }
+
+    fn eval_bool(&mut self, exp: &Expression<'sc>) -> Result<bool, Interrupt<'sc>> {
+        match self.eval_exp(exp)? {
+            Value::Boolean(b) => Ok(b),
+            value => error(format!("Expected a boolean, found {}", value)),
+        }
+    }
+
+    fn eval_exp(&mut self, exp: &Expression<'sc>) -> Eval<'sc> {
+        match exp {
+            Expression::Literal { value, .. } => Ok(Value::from(value.clone())),
+            Expression::VariableExpression { name, .. } => match self.lookup(&name.name()) {
+                Some(binding) => Ok(binding.value.clone()),
+                None => error(format!("Unbound variable {}", name.name())),
+            },
+            Expression::BinOp {
+                op2, left, right, ..
+            } => match op2 {
+                Op2::And => {
+                    let value = self.eval_bool(left)? && self.eval_bool(right)?;
+                    Ok(Value::Boolean(value))
+                }
+                Op2::Or => {
+                    let value = self.eval_bool(left)? || self.eval_bool(right)?;
+                    Ok(Value::Boolean(value))
+                }
+                Op2::Eq => {
+                    let left = self.eval_exp(left)?;
+                    let right = self.eval_exp(right)?;
+                    Ok(Value::Boolean(left == right))
+                }
+                Op2::Lt | Op2::Le | Op2::Gt | Op2::Ge => {
+                    let left = self.eval_exp(left)?;
+                    let right = self.eval_exp(right)?;
+                    let ordering = match compare_integers(&left, &right) {
+                        Some(ordering) => ordering,
+                        None => return error(format!("Can't compare {} with {}", left, right)),
+                    };
+                    let value = match op2 {
+                        Op2::Lt => ordering == Ordering::Less,
+                        Op2::Le => ordering != Ordering::Greater,
+                        Op2::Gt => ordering == Ordering::Greater,
+                        _ => ordering != Ordering::Less,
+                    };
+                    Ok(Value::Boolean(value))
+                }
+            },
+            Expression::Unit { .. } => Ok(Value::Unit),
+            Expression::Array { contents, .. } => {
+                let mut values = vec![];
+                for exp in contents.iter() {
+                    values.push(self.eval_exp(exp)?);
+                }
+                Ok(Value::Array(values))
+            }
+            Expression::CodeBlock { contents, .. } => self.eval_scoped(&contents.contents),
+            Expression::IfExp {
+                condition,
+                then,
+                r#else,
+                ..
+            } => {
+                if self.eval_bool(condition)? {
+                    self.eval_exp(then)
+                } else {
+                    match r#else {
+                        Some(r#else) => self.eval_exp(r#else),
+                        None => Ok(Value::Unit),
+                    }
+                }
+            }
+            Expression::Match { .. } => undesugared(),
+            Expression::Tuple { elems, .. } => {
+                let mut values = vec![];
+                for exp in elems.iter() {
+                    values.push(self.eval_exp(exp)?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            Expression::StructExpression {
+                struct_name,
+                fields,
+                ..
+            } => {
+                // the desugarer has already checked the fields against the
+                // declaration
+                let declaration = match self.lookup_struct(struct_name.primary_name) {
+                    Some(declaration) => declaration.clone(),
+                    None => return error(format!("Unknown struct {}", struct_name.name())),
+                };
+                // the fields are evaluated in the order they are written, but
+                // stored in the order they are declared
+                let mut values = vec![];
+                for field in fields.iter() {
+                    values.push((field.name.primary_name, self.eval_exp(&field.value)?));
+                }
+                values.sort_by_key(|(name, _)| {
+                    declaration
+                        .fields
+                        .iter()
+                        .position(|declared| declared.name.primary_name == *name)
+                });
+                Ok(Value::Struct {
+                    struct_name: struct_name.primary_name,
+                    fields: values,
+                })
+            }
+            Expression::EnumInstantiation {
+                enum_name,
+                variant_name,
+                payload,
+                ..
+            } => {
+                let declaration = match self.lookup_enum(enum_name.primary_name) {
+                    Some(declaration) => declaration,
+                    None => return error(format!("Unknown enum {}", enum_name.name())),
+                };
+                let arity = match declaration.variant(variant_name.primary_name) {
+                    Some(variant) => variant.payload.len(),
+                    None => {
+                        return error(format!(
+                            "Enum {} has no variant {}",
+                            enum_name.name(),
+                            variant_name.name()
+                        ))
+                    }
+                };
+                if arity != payload.len() {
+                    return error(format!(
+                        "Variant {}::{} takes {} values, found {}",
+                        enum_name.name(),
+                        variant_name.name(),
+                        arity,
+                        payload.len()
+                    ));
+                }
+                let mut values = vec![];
+                for exp in payload.iter() {
+                    values.push(self.eval_exp(exp)?);
+                }
+                Ok(Value::Enum {
+                    enum_name: enum_name.primary_name,
+                    variant_name: variant_name.primary_name,
+                    payload: values,
+                })
+            }
+            Expression::TupleIndex { prefix, index, .. } => {
+                let elems = match self.eval_exp(prefix)? {
+                    Value::Tuple(elems) => elems,
+                    value => return error(format!("Can't index into {}", value)),
+                };
+                match elems.into_iter().nth(*index) {
+                    Some(value) => Ok(value),
+                    None => error(format!("Index {} is out of bounds", index)),
+                }
+            }
+            Expression::ArrayIndex { prefix, index, .. } => {
+                let elems = match self.eval_exp(prefix)? {
+                    Value::Array(elems) => elems,
+                    value => return error(format!("Can't index into {}", value)),
+                };
+                match elems.into_iter().nth(*index) {
+                    Some(value) => Ok(value),
+                    None => error(format!("Index {} is out of bounds", index)),
+                }
+            }
+            Expression::IndexFromEnd { prefix, index, .. } => {
+                let elems = match self.eval_exp(prefix)? {
+                    Value::Tuple(elems) | Value::Array(elems) => elems,
+                    value => return error(format!("Can't index into {}", value)),
+                };
+                match elems.into_iter().rev().nth(*index) {
+                    Some(value) => Ok(value),
+                    None => error(format!("Index {} from the end is out of bounds", index)),
+                }
+            }
+            Expression::LengthCheck {
+                prefix,
+                length,
+                at_least,
+                ..
+            } => match self.eval_exp(prefix)? {
+                Value::Tuple(elems) | Value::Array(elems) if *at_least => {
+                    Ok(Value::Boolean(elems.len() >= *length))
+                }
+                Value::Tuple(elems) | Value::Array(elems) => {
+                    Ok(Value::Boolean(elems.len() == *length))
+                }
+                _ => Ok(Value::Boolean(false)),
+            },
+            Expression::SubfieldExpression {
+                prefix, field_name, ..
+            } => match self.eval_exp(prefix)? {
+                Value::Struct {
+                    struct_name,
+                    fields,
+                } => match fields
+                    .into_iter()
+                    .find(|(name, _)| *name == field_name.primary_name)
+                {
+                    Some((_, value)) => Ok(value),
+                    None => error(format!(
+                        "Struct {} has no field {}",
+                        struct_name, field_name.primary_name
+                    )),
+                },
+                value => error(format!("Expected a struct, found {}", value)),
+            },
+            Expression::StructKindCheck {
+                prefix,
+                struct_name: expected,
+                ..
+            } => match self.eval_exp(prefix)? {
+                Value::Struct { struct_name, .. } => {
+                    Ok(Value::Boolean(struct_name == expected.primary_name))
+                }
+                _ => Ok(Value::Boolean(false)),
+            },
+            Expression::VariantCheck {
+                prefix,
+                enum_name: expected_enum,
+                variant_name: expected_variant,
+                ..
+            } => match self.eval_exp(prefix)? {
+                Value::Enum {
+                    enum_name,
+                    variant_name,
+                    ..
+                } => Ok(Value::Boolean(
+                    enum_name == expected_enum.primary_name
+                        && variant_name == expected_variant.primary_name,
+                )),
+                _ => Ok(Value::Boolean(false)),
+            },
+            Expression::VariantPayload { prefix, index, .. } => {
+                let payload = match self.eval_exp(prefix)? {
+                    Value::Enum { payload, .. } => payload,
+                    value => return error(format!("{} is not an enum variant", value)),
+                };
+                match payload.into_iter().nth(*index) {
+                    Some(value) => Ok(value),
+                    None => error(format!("Variant payload has no element {}", index)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        desugar::desugar_tree,
+        interpreter::{interpret, run},
+        language::{constructors::*, Literal},
+        parser::parse,
+        value::Value,
+    };
+
+    #[test]
+    fn interpret_declarations() {
+        let program = tree(vec![
+            variable_declaraction("x", literal(u32_(4)), true),
+            reassignment(variable("x"), literal(u32_(5))),
+            expression(variable("x")),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(5)));
+    }
+
+    #[test]
+    fn interpret_immutable_reassignment() {
+        let program = tree(vec![
+            variable_declaraction("x", literal(u32_(4)), false),
+            reassignment(variable("x"), literal(u32_(5))),
+        ]);
+        assert!(interpret(program).is_err());
+    }
+
+    #[test]
+    fn interpret_scoping() {
+        let program = tree(vec![
+            variable_declaraction("x", literal(u32_(1)), false),
+            expression(block(vec![
+                variable_declaraction("x", literal(u32_(2)), false),
+                expression(variable("x")),
+            ])),
+            expression(variable("x")),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn interpret_while_loop() {
+        let program = tree(vec![
+            variable_declaraction("done", literal(boolean(false)), true),
+            variable_declaraction("count", literal(u32_(0)), true),
+            while_loop(
+                binop_eq(variable("done"), literal(boolean(false))),
+                vec![
+                    reassignment(variable("count"), literal(u32_(1))),
+                    reassignment(variable("done"), literal(boolean(true))),
+                ],
+            ),
+            expression(variable("count")),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn interpret_return() {
+        let program = tree(vec![
+            if_statement(
+                literal(boolean(true)),
+                block(vec![return_(literal(u32_(1)))]),
+                None,
+            ),
+            expression(literal(u32_(2))),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn interpret_projections() {
+        let program = tree(vec![
+            struct_declaration("Point", vec![("x", "u32")]),
+            variable_declaraction(
+                "pair",
+                tuple(vec![
+                    literal(u32_(1)),
+                    struct_("Point", vec![struct_field("x", literal(u32_(5)))]),
+                ]),
+                false,
+            ),
+            expression(binop_and(
+                struct_kind_check(tuple_index(variable("pair"), 1), "Point"),
+                binop_eq(
+                    subfield(tuple_index(variable("pair"), 1), "x"),
+                    tuple_index(tuple(vec![literal(u32_(5))]), 0),
+                ),
+            )),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::Boolean(true)));
+        let program = tree(vec![expression(tuple_index(
+            tuple(vec![literal(u32_(1))]),
+            1,
+        ))]);
+        assert!(interpret(program).is_err());
+    }
+
+    #[test]
+    fn interpret_match() {
+        let program = tree(vec![
+            struct_declaration("Point", vec![("x", "u32"), ("y", "u32")]),
+            variable_declaraction(
+                "foo",
+                struct_(
+                    "Point",
+                    vec![
+                        struct_field("x", literal(u32_(5))),
+                        struct_field("y", literal(u32_(7))),
+                    ],
+                ),
+                false,
+            ),
+            match_(
+                variable("foo"),
+                vec![
+                    match_branch(
+                        match_scrutinee(struct_scrutinee(
+                            "Point",
+                            vec![
+                                struct_scrutinee_field("x", variable_scrutinee("x")),
+                                struct_scrutinee_field("y", literal_scrutinee(u32_(0))),
+                            ],
+                        )),
+                        variable("x"),
+                    ),
+                    match_branch(
+                        match_scrutinee(struct_scrutinee(
+                            "Point",
+                            vec![
+                                struct_scrutinee_field("x", variable_scrutinee("x")),
+                                struct_scrutinee_field("y", variable_scrutinee("y")),
+                            ],
+                        )),
+                        variable("y"),
+                    ),
+                ],
+            ),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::U32(7)));
+    }
+
+    #[test]
+    fn interpret_switch() {
+        let program = |opcode| {
+            tree(vec![switch(
+                literal(opcode),
+                u32_(2),
+                vec![Some(0), None, Some(1)],
+                vec![literal(u32_(10)), literal(u32_(20))],
+                Some(literal(u32_(0))),
+            )])
+        };
+        assert_eq!(interpret(program(u32_(2))), Ok(Value::U32(10)));
+        assert_eq!(interpret(program(u32_(4))), Ok(Value::U32(20)));
+        // below the table, in a gap, past the end, and of another width
+        for opcode in [u32_(1), u32_(3), u32_(5), Literal::U8(2)] {
+            assert_eq!(interpret(program(opcode)), Ok(Value::U32(0)));
+        }
+    }
+
+    #[test]
+    fn interpret_switch_in_loop() {
+        let src = "
+            let mut state = 0;
+            while state < 4 {
+                state = match state { 0 => 1, 1 => 2, 2 => 3, 3 => 4, _ => 4 };
+            }
+            state
+        ";
+        // the loop's `match` is lowered to a single switch before it runs
+        let (tree, _) = desugar_tree(parse(src).unwrap()).unwrap();
+        let desugared = tree.to_string();
+        assert_eq!(desugared.matches("Switch {").count(), 1);
+        assert!(!desugared.contains("Match"));
+        assert_eq!(run(tree), Ok(Value::U32(4)));
+        assert_eq!(interpret(parse(src).unwrap()), Ok(Value::U32(4)));
+    }
+
+    #[test]
+    fn interpret_destructuring_declarations() {
+        let program = tree(vec![
+            struct_declaration("Point", vec![("x", "u32"), ("y", "u32")]),
+            destructuring_declaration(
+                struct_scrutinee(
+                    "Point",
+                    vec![
+                        struct_scrutinee_field("x", variable_scrutinee("x")),
+                        struct_scrutinee_field("y", variable_scrutinee("y")),
+                    ],
+                ),
+                struct_(
+                    "Point",
+                    vec![
+                        struct_field("x", literal(u32_(1))),
+                        struct_field("y", literal(u32_(2))),
+                    ],
+                ),
+                true,
+            ),
+            reassignment(variable("x"), literal(u32_(3))),
+            expression(tuple(vec![variable("x"), variable("y")])),
+        ]);
+        assert_eq!(
+            interpret(program),
+            Ok(Value::Tuple(vec![Value::U32(3), Value::U32(2)]))
+        );
+
+        let program = tree(vec![destructuring_declaration(
+            tuple_scrutinee(vec![variable_scrutinee("a"), variable_scrutinee("b")]),
+            literal(u32_(1)),
+            false,
+        )]);
+        assert!(interpret(program).is_err());
+    }
+
+    #[test]
+    fn interpret_struct_declarations() {
+        let point = || struct_declaration("Point", vec![("x", "u32"), ("y", "u32")]);
+        // fields can be given in any order
+        let program = tree(vec![
+            point(),
+            expression(binop_eq(
+                struct_(
+                    "Point",
+                    vec![
+                        struct_field("y", literal(u32_(2))),
+                        struct_field("x", literal(u32_(1))),
+                    ],
+                ),
+                struct_(
+                    "Point",
+                    vec![
+                        struct_field("x", literal(u32_(1))),
+                        struct_field("y", literal(u32_(2))),
+                    ],
+                ),
+            )),
+        ]);
+        assert_eq!(interpret(program), Ok(Value::Boolean(true)));
+
+        let program = tree(vec![
+            point(),
+            expression(struct_("Point", vec![struct_field("x", literal(u32_(1)))])),
+        ]);
+        assert_eq!(
+            interpret(program),
+            Err("Struct Point is missing field y".to_string())
+        );
+
+        let program = tree(vec![
+            point(),
+            expression(struct_(
+                "Point",
+                vec![
+                    struct_field("x", literal(u32_(1))),
+                    struct_field("y", literal(u32_(2))),
+                    struct_field("z", literal(u32_(3))),
+                ],
+            )),
+        ]);
+        assert_eq!(
+            interpret(program),
+            Err("Struct Point has no field z".to_string())
+        );
+
+        let program = tree(vec![
+            point(),
+            expression(struct_(
+                "Point",
+                vec![
+                    struct_field("x", literal(u32_(1))),
+                    struct_field("x", literal(u32_(2))),
+                ],
+            )),
+        ]);
+        assert_eq!(
+            interpret(program),
+            Err("Field x is given more than once".to_string())
+        );
+
+        let program = tree(vec![expression(struct_("Point", vec![]))]);
+        assert_eq!(interpret(program), Err("Unknown type Point".to_string()));
 
-pub fn interpret<'sc>(tree: Tree<'sc>) -> Expression<'sc> {
-    unimplemented!()
+        // a struct declared in a block is gone once the block ends
+        let program = tree(vec![
+            expression(block(vec![point()])),
+            expression(struct_(
+                "Point",
+                vec![
+                    struct_field("x", literal(u32_(1))),
+                    struct_field("y", literal(u32_(2))),
+                ],
+            )),
+        ]);
+        assert_eq!(interpret(program), Err("Unknown type Point".to_string()));
+    }
 }
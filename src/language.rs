@@ -1,7 +1,55 @@
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::fmt::{self, write};
+use std::fmt;
 
-pub type Namespace<'sc> = HashMap<String, Expression<'sc>>;
+use crate::span::Span;
+use crate::value::Value;
+
+/// What the desugarer knows about the program at the point of a `match`:
+/// the values of the variables in scope, and the types declared so far.
+#[derive(Debug, Clone, Default)]
+pub struct Namespace<'sc> {
+    values: HashMap<String, Value<'sc>>,
+    enums: HashMap<&'sc str, EnumDeclaration<'sc>>,
+    structs: HashMap<&'sc str, StructDeclaration<'sc>>,
+}
+
+impl<'sc> Namespace<'sc> {
+    pub fn get(&self, name: &str) -> Option<&Value<'sc>> {
+        self.values.get(name)
+    }
+
+    pub fn insert(&mut self, name: String, value: Value<'sc>) {
+        self.values.insert(name, value);
+    }
+
+    /// The same types, with none of the values.
+    pub fn types(&self) -> Namespace<'sc> {
+        Namespace {
+            values: HashMap::new(),
+            enums: self.enums.clone(),
+            structs: self.structs.clone(),
+        }
+    }
+
+    pub fn get_enum(&self, name: &str) -> Option<&EnumDeclaration<'sc>> {
+        self.enums.get(name)
+    }
+
+    pub fn declare_enum(&mut self, declaration: EnumDeclaration<'sc>) {
+        self.enums
+            .insert(declaration.name.primary_name, declaration);
+    }
+
+    pub fn get_struct(&self, name: &str) -> Option<&StructDeclaration<'sc>> {
+        self.structs.get(name)
+    }
+
+    pub fn declare_struct(&mut self, declaration: StructDeclaration<'sc>) {
+        self.structs
+            .insert(declaration.name.primary_name, declaration);
+    }
+}
 
 #[derive(Debug)]
 pub struct Tree<'sc> {
@@ -21,6 +69,9 @@ impl<'sc> fmt::Display for Tree<'sc> {
     }
 }
 
+// an `if` holds three expressions, but nodes are built once and then only
+// walked, so it isn't worth boxing them
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum Node<'sc> {
     Declaration(Declaration<'sc>),
@@ -29,13 +80,36 @@ pub enum Node<'sc> {
     ReturnStatement(ReturnStatement<'sc>),
     MatchStatement(MatchStatement<'sc>),
     IfExpression(IfExpression<'sc>),
+    Switch(Switch<'sc>),
+}
+
+impl<'sc> Node<'sc> {
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            Node::Declaration(Declaration::VariableDeclaration(decl)) => decl.span,
+            Node::Declaration(Declaration::DestructuringDeclaration(decl)) => decl.span,
+            Node::Declaration(Declaration::Reassignment(reassignment)) => reassignment.span,
+            Node::Declaration(Declaration::EnumDeclaration(enum_declaration)) => {
+                enum_declaration.span
+            }
+            Node::Declaration(Declaration::StructDeclaration(struct_declaration)) => {
+                struct_declaration.span
+            }
+            Node::Expression(exp) => exp.span(),
+            Node::WhileLoop(while_loop) => while_loop.span,
+            Node::ReturnStatement(return_statement) => return_statement.span,
+            Node::MatchStatement(match_statement) => match_statement.span,
+            Node::IfExpression(if_expression) => if_expression.span,
+            Node::Switch(switch) => switch.span,
+        }
+    }
 }
 
 impl<'sc> fmt::Display for Node<'sc> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Node::Declaration(declaration) => write!(f, "{}", declaration.to_string()),
-            node => write!(f, "{}", format!("{:?}", node)),
+            Node::Declaration(declaration) => write!(f, "{}", declaration),
+            node => write!(f, "{:?}", node),
         }
     }
 }
@@ -43,16 +117,24 @@ impl<'sc> fmt::Display for Node<'sc> {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Declaration<'sc> {
     VariableDeclaration(VariableDeclaration<'sc>),
+    DestructuringDeclaration(DestructuringDeclaration<'sc>),
     Reassignment(Reassignment<'sc>),
+    EnumDeclaration(EnumDeclaration<'sc>),
+    StructDeclaration(StructDeclaration<'sc>),
 }
 
 impl<'sc> fmt::Display for Declaration<'sc> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Declaration::VariableDeclaration(variable_decl) => {
-                write!(f, "{}", variable_decl.to_string())
+                write!(f, "{}", variable_decl)
+            }
+            Declaration::DestructuringDeclaration(destructuring_decl) => {
+                write!(f, "{}", destructuring_decl)
             }
-            Declaration::Reassignment(reassign_decl) => write!(f, "{}", reassign_decl.to_string()),
+            Declaration::Reassignment(reassign_decl) => write!(f, "{}", reassign_decl),
+            Declaration::EnumDeclaration(enum_decl) => write!(f, "{}", enum_decl),
+            Declaration::StructDeclaration(struct_decl) => write!(f, "{}", struct_decl),
         }
     }
 }
@@ -61,41 +143,146 @@ impl<'sc> fmt::Display for Declaration<'sc> {
 pub enum Expression<'sc> {
     Literal {
         value: Literal<'sc>,
+        span: Span<'sc>,
     },
     VariableExpression {
         name: Ident<'sc>,
+        span: Span<'sc>,
     },
     BinOp {
         op2: Op2,
         left: Box<Expression<'sc>>,
         right: Box<Expression<'sc>>,
+        span: Span<'sc>,
+    },
+    Unit {
+        span: Span<'sc>,
     },
-    Unit {},
     Array {
         contents: Vec<Expression<'sc>>,
+        span: Span<'sc>,
     },
     CodeBlock {
         contents: CodeBlock<'sc>,
+        span: Span<'sc>,
     },
     IfExp {
         condition: Box<Expression<'sc>>,
         then: Box<Expression<'sc>>,
         r#else: Option<Box<Expression<'sc>>>,
+        span: Span<'sc>,
+    },
+    /// A `match` used for its value, e.g. on the right of a `let`. The
+    /// desugarer turns it into `IfExp`s and blocks.
+    Match {
+        primary: Box<Expression<'sc>>,
+        branches: Vec<MatchBranch<'sc>>,
+        span: Span<'sc>,
     },
     Tuple {
         elems: Vec<Expression<'sc>>,
+        span: Span<'sc>,
     },
     StructExpression {
         struct_name: Ident<'sc>,
         fields: Vec<StructExpressionField<'sc>>,
+        span: Span<'sc>,
+    },
+    /// `Shape::Circle(5)`, or `Shape::Empty` for a variant without a payload.
+    EnumInstantiation {
+        enum_name: Ident<'sc>,
+        variant_name: Ident<'sc>,
+        payload: Vec<Expression<'sc>>,
+        span: Span<'sc>,
+    },
+    /// `prefix.0`, the `index`th element of a tuple.
+    TupleIndex {
+        prefix: Box<Expression<'sc>>,
+        index: usize,
+        span: Span<'sc>,
+    },
+    /// `prefix[0]`, the `index`th element of an array.
+    ArrayIndex {
+        prefix: Box<Expression<'sc>>,
+        index: usize,
+        span: Span<'sc>,
+    },
+    /// `prefix.field_name`
+    SubfieldExpression {
+        prefix: Box<Expression<'sc>>,
+        field_name: Ident<'sc>,
+        span: Span<'sc>,
+    },
+    /// The `index`th element of a tuple or array counting back from the end,
+    /// so `0` is the last one. Like the checks below, it's only generated when
+    /// desugaring.
+    IndexFromEnd {
+        prefix: Box<Expression<'sc>>,
+        index: usize,
+        span: Span<'sc>,
+    },
+    /// Whether `prefix` is a tuple or array of `length` elements, or of at
+    /// least that many if `at_least` is set.
+    LengthCheck {
+        prefix: Box<Expression<'sc>>,
+        length: usize,
+        at_least: bool,
+        span: Span<'sc>,
+    },
+    /// Whether `prefix` is a struct named `struct_name`. There's no surface
+    /// syntax for this, it's only generated when desugaring.
+    StructKindCheck {
+        prefix: Box<Expression<'sc>>,
+        struct_name: Ident<'sc>,
+        span: Span<'sc>,
+    },
+    /// Whether `prefix` is the `variant_name` variant of `enum_name`. Also
+    /// only generated when desugaring.
+    VariantCheck {
+        prefix: Box<Expression<'sc>>,
+        enum_name: Ident<'sc>,
+        variant_name: Ident<'sc>,
+        span: Span<'sc>,
+    },
+    /// The `index`th element of the payload of the enum variant `prefix`.
+    VariantPayload {
+        prefix: Box<Expression<'sc>>,
+        index: usize,
+        span: Span<'sc>,
     },
 }
 
+impl<'sc> Expression<'sc> {
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            Expression::Literal { span, .. }
+            | Expression::VariableExpression { span, .. }
+            | Expression::BinOp { span, .. }
+            | Expression::Unit { span }
+            | Expression::Array { span, .. }
+            | Expression::CodeBlock { span, .. }
+            | Expression::IfExp { span, .. }
+            | Expression::Match { span, .. }
+            | Expression::Tuple { span, .. }
+            | Expression::StructExpression { span, .. }
+            | Expression::TupleIndex { span, .. }
+            | Expression::ArrayIndex { span, .. }
+            | Expression::SubfieldExpression { span, .. }
+            | Expression::IndexFromEnd { span, .. }
+            | Expression::LengthCheck { span, .. }
+            | Expression::StructKindCheck { span, .. }
+            | Expression::EnumInstantiation { span, .. }
+            | Expression::VariantCheck { span, .. }
+            | Expression::VariantPayload { span, .. } => *span,
+        }
+    }
+}
+
 impl<'sc> fmt::Display for Expression<'sc> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Expression::Literal { value } => write!(f, "{}", value.to_string()),
-            exp => write!(f, "{}", format!("{:?}", exp)),
+            Expression::Literal { value, .. } => write!(f, "{}", value),
+            exp => write!(f, "{:?}", exp),
         }
     }
 }
@@ -103,18 +290,25 @@ impl<'sc> fmt::Display for Expression<'sc> {
 #[derive(Debug, Clone, PartialEq)]
 pub enum Op2 {
     And,
+    Or,
     Eq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct WhileLoop<'sc> {
     pub condition: Expression<'sc>,
     pub body: CodeBlock<'sc>,
+    pub span: Span<'sc>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct ReturnStatement<'sc> {
     pub expr: Expression<'sc>,
+    pub span: Span<'sc>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -127,6 +321,7 @@ pub struct VariableDeclaration<'sc> {
     pub name: Ident<'sc>,
     pub body: Expression<'sc>,
     pub is_mutable: bool,
+    pub span: Span<'sc>,
 }
 
 impl<'sc> fmt::Display for VariableDeclaration<'sc> {
@@ -136,16 +331,165 @@ impl<'sc> fmt::Display for VariableDeclaration<'sc> {
         if self.is_mutable {
             output.push_str("mut ");
         }
-        output.push_str(self.name.primary_name);
+        output.push_str(&self.name.name());
         output.push_str(" = ");
         output.push_str(&self.body.to_string());
         write!(f, "{}", output)
     }
 }
 
+/// `let (a, b) = pair`, a `let` with a pattern on its left. The pattern must
+/// match every value, and `is_mutable` applies to each variable it binds.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DestructuringDeclaration<'sc> {
+    pub pattern: Scrutinee<'sc>,
+    pub body: Expression<'sc>,
+    pub is_mutable: bool,
+    pub span: Span<'sc>,
+}
+
+impl<'sc> fmt::Display for DestructuringDeclaration<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mutability = if self.is_mutable { "mut " } else { "" };
+        write!(f, "let {}{} = {}", mutability, self.pattern, self.body)
+    }
+}
+
 #[derive(Debug, Clone, PartialEq)]
 pub struct Ident<'sc> {
     pub primary_name: &'sc str,
+    // set on identifiers made up by the compiler, which keeps them apart from
+    // each other and from anything the user wrote
+    pub gensym: Option<usize>,
+    pub span: Span<'sc>,
+}
+
+impl<'sc> Ident<'sc> {
+    pub fn new(primary_name: &'sc str, span: Span<'sc>) -> Self {
+        Ident {
+            primary_name,
+            gensym: None,
+            span,
+        }
+    }
+
+    /// The name bindings are resolved by. Generated identifiers get a `#`
+    /// suffix, which the lexer never produces.
+    pub fn name(&self) -> Cow<'sc, str> {
+        match self.gensym {
+            None => Cow::Borrowed(self.primary_name),
+            Some(n) => Cow::Owned(format!("{}#{}", self.primary_name, n)),
+        }
+    }
+}
+
+/// Hands out fresh identifiers for the bindings the compiler introduces.
+#[derive(Debug, Default)]
+pub struct NameGenerator {
+    next: usize,
+}
+
+impl NameGenerator {
+    /// An identifier, based on `primary_name`, that is distinct from every
+    /// other one this generator made and from any user identifier.
+    pub fn fresh<'sc>(&mut self, primary_name: &'sc str, span: Span<'sc>) -> Ident<'sc> {
+        let gensym = self.next;
+        self.next += 1;
+        Ident {
+            primary_name,
+            gensym: Some(gensym),
+            span,
+        }
+    }
+}
+
+/// `enum Shape { Circle(u32), Rect(u32, u32), Empty }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumDeclaration<'sc> {
+    pub name: Ident<'sc>,
+    pub variants: Vec<EnumVariant<'sc>>,
+    pub span: Span<'sc>,
+}
+
+impl<'sc> EnumDeclaration<'sc> {
+    pub fn variant(&self, name: &str) -> Option<&EnumVariant<'sc>> {
+        self.variants
+            .iter()
+            .find(|variant| variant.name.primary_name == name)
+    }
+}
+
+impl<'sc> fmt::Display for EnumDeclaration<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let variants = self
+            .variants
+            .iter()
+            .map(EnumVariant::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "enum {} {{ {} }}", self.name.name(), variants.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnumVariant<'sc> {
+    pub name: Ident<'sc>,
+    // the names of the types in the payload, which are only counted for now
+    pub payload: Vec<Ident<'sc>>,
+}
+
+impl<'sc> fmt::Display for EnumVariant<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name.name())?;
+        if !self.payload.is_empty() {
+            let payload = self
+                .payload
+                .iter()
+                .map(|type_name| type_name.name())
+                .collect::<Vec<_>>();
+            write!(f, "({})", payload.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// `struct Point { x: u32, y: u32 }`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructDeclaration<'sc> {
+    pub name: Ident<'sc>,
+    pub fields: Vec<StructField<'sc>>,
+    pub span: Span<'sc>,
+}
+
+impl<'sc> StructDeclaration<'sc> {
+    pub fn field(&self, name: &str) -> Option<&StructField<'sc>> {
+        self.fields
+            .iter()
+            .find(|field| field.name.primary_name == name)
+    }
+}
+
+impl<'sc> fmt::Display for StructDeclaration<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self
+            .fields
+            .iter()
+            .map(StructField::to_string)
+            .collect::<Vec<_>>();
+        write!(f, "struct {} {{ {} }}", self.name.name(), fields.join(", "))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct StructField<'sc> {
+    pub name: Ident<'sc>,
+    // the name of the type of the field, which isn't checked yet
+    pub r#type: Ident<'sc>,
+}
+
+impl<'sc> fmt::Display for StructField<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.name.name(), self.r#type.name())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -154,6 +498,7 @@ pub struct Reassignment<'sc> {
     pub lhs: Box<Expression<'sc>>,
     // the expression that is being assigned to the lhs
     pub rhs: Expression<'sc>,
+    pub span: Span<'sc>,
 }
 
 impl<'sc> fmt::Display for Reassignment<'sc> {
@@ -177,16 +522,29 @@ pub enum Literal<'sc> {
     Byte(u8),
 }
 
+impl<'sc> Literal<'sc> {
+    /// The value of an integer literal, of any width.
+    pub fn integer_value(&self) -> Option<u64> {
+        match self {
+            Literal::U8(n) | Literal::Byte(n) => Some(*n as u64),
+            Literal::U16(n) => Some(*n as u64),
+            Literal::U32(n) => Some(*n as u64),
+            Literal::U64(n) => Some(*n),
+            Literal::String(_) | Literal::Boolean(_) => None,
+        }
+    }
+}
+
 impl<'sc> fmt::Display for Literal<'sc> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            Literal::U8(lit) => write!(f, "{}", lit.to_string()),
-            Literal::U16(lit) => write!(f, "{}", lit.to_string()),
-            Literal::U32(lit) => write!(f, "{}", lit.to_string()),
-            Literal::U64(lit) => write!(f, "{}", lit.to_string()),
-            Literal::String(lit) => write!(f, "{}", lit.to_string()),
-            Literal::Boolean(lit) => write!(f, "{}", lit.to_string()),
-            Literal::Byte(lit) => write!(f, "{}", lit.to_string()),
+            Literal::U8(lit) => write!(f, "{}", lit),
+            Literal::U16(lit) => write!(f, "{}", lit),
+            Literal::U32(lit) => write!(f, "{}", lit),
+            Literal::U64(lit) => write!(f, "{}", lit),
+            Literal::String(lit) => write!(f, "{}", lit),
+            Literal::Boolean(lit) => write!(f, "{}", lit),
+            Literal::Byte(lit) => write!(f, "{}", lit),
         }
     }
 }
@@ -201,6 +559,7 @@ pub struct StructExpressionField<'sc> {
 pub struct MatchStatement<'sc> {
     pub primary: Expression<'sc>,
     pub branches: Vec<MatchBranch<'sc>>,
+    pub span: Span<'sc>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -208,12 +567,33 @@ pub struct IfExpression<'sc> {
     pub primary: Expression<'sc>,
     pub left: Expression<'sc>,
     pub right: Option<Expression<'sc>>,
+    pub span: Span<'sc>,
+}
+
+/// A jump table over the integers from `low` up, which runs the block for
+/// the value of `primary`, or `default` if the table has none for it. The
+/// desugarer builds one for a `match` whose arms are integer literals.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Switch<'sc> {
+    pub primary: Expression<'sc>,
+    /// The first value in the table, which also fixes the width of the
+    /// integers it switches on.
+    pub low: Literal<'sc>,
+    /// For the value `low + i`, the index into `blocks` of the block to run.
+    pub table: Vec<Option<usize>>,
+    pub blocks: Vec<Expression<'sc>>,
+    pub default: Option<Expression<'sc>>,
+    pub span: Span<'sc>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct MatchBranch<'sc> {
     pub condition: MatchScrutinee<'sc>,
+    /// `if guard`, tested once the pattern has matched, with its bindings in
+    /// scope.
+    pub guard: Option<Expression<'sc>>,
     pub result: Expression<'sc>,
+    pub span: Span<'sc>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
@@ -226,24 +606,220 @@ pub enum MatchScrutinee<'sc> {
 pub enum Scrutinee<'sc> {
     Literal {
         value: Literal<'sc>,
+        span: Span<'sc>,
     },
     VariableExpression {
         name: Ident<'sc>,
+        span: Span<'sc>,
     },
     Tuple {
         elems: Vec<Scrutinee<'sc>>,
+        span: Span<'sc>,
+    },
+    /// `[a, b]`, or `[a, .., b]` to match arrays of any length that fits.
+    Array {
+        elems: Vec<Scrutinee<'sc>>,
+        span: Span<'sc>,
+    },
+    /// `Shape::Circle(r)`, matching one variant of an enum and its payload.
+    EnumVariant {
+        enum_name: Ident<'sc>,
+        variant_name: Ident<'sc>,
+        payload: Vec<Scrutinee<'sc>>,
+        span: Span<'sc>,
     },
     StructScrutinee {
         struct_name: Ident<'sc>,
         fields: Vec<StructScrutineeField<'sc>>,
+        // whether the pattern ends in `..`, which ignores the fields it
+        // doesn't mention
+        rest: bool,
+        span: Span<'sc>,
+    },
+    /// `start..=end` or `start..end` over integers of one width. Either end
+    /// can be left out, but not both.
+    Range {
+        start: Option<Literal<'sc>>,
+        end: Option<Literal<'sc>>,
+        inclusive: bool,
+        span: Span<'sc>,
+    },
+    /// `a | b`, matching whatever any of the alternatives match. Every
+    /// alternative has to bind the same variables.
+    Or {
+        alternatives: Vec<Scrutinee<'sc>>,
+        span: Span<'sc>,
     },
+    /// `name @ sub`, matching what `sub` matches and binding the whole value
+    /// to `name` as well.
+    Binding {
+        name: Ident<'sc>,
+        sub: Box<Scrutinee<'sc>>,
+        span: Span<'sc>,
+    },
+    /// `_` inside another pattern, matching anything without binding it.
+    Wildcard {
+        span: Span<'sc>,
+    },
+    /// `..` as an element of a tuple or array pattern, standing for any number of
+    /// elements. It isn't a pattern on its own.
+    Rest {
+        span: Span<'sc>,
+    },
+}
+
+impl<'sc> Scrutinee<'sc> {
+    /// The variables bound by the pattern, in the order they appear.
+    pub fn bindings(&self) -> Vec<&Ident<'sc>> {
+        match self {
+            Scrutinee::Literal { .. }
+            | Scrutinee::Range { .. }
+            | Scrutinee::Wildcard { .. }
+            | Scrutinee::Rest { .. } => {
+                vec![]
+            }
+            Scrutinee::VariableExpression { name, .. } => vec![name],
+            Scrutinee::Tuple { elems, .. }
+            | Scrutinee::Array { elems, .. }
+            | Scrutinee::EnumVariant { payload: elems, .. } => {
+                elems.iter().flat_map(Scrutinee::bindings).collect()
+            }
+            Scrutinee::StructScrutinee { fields, .. } => fields
+                .iter()
+                .flat_map(|field| field.scrutinee.bindings())
+                .collect(),
+            Scrutinee::Binding { name, sub, .. } => {
+                let mut bindings = vec![name];
+                bindings.extend(sub.bindings());
+                bindings
+            }
+            // the alternatives all bind the same variables
+            Scrutinee::Or { alternatives, .. } => alternatives
+                .first()
+                .map(Scrutinee::bindings)
+                .unwrap_or_default(),
+        }
+    }
+
+    pub fn span(&self) -> Span<'sc> {
+        match self {
+            Scrutinee::Literal { span, .. }
+            | Scrutinee::VariableExpression { span, .. }
+            | Scrutinee::Tuple { span, .. }
+            | Scrutinee::Array { span, .. }
+            | Scrutinee::EnumVariant { span, .. }
+            | Scrutinee::StructScrutinee { span, .. }
+            | Scrutinee::Range { span, .. }
+            | Scrutinee::Or { span, .. }
+            | Scrutinee::Binding { span, .. }
+            | Scrutinee::Wildcard { span }
+            | Scrutinee::Rest { span } => *span,
+        }
+    }
+
+    /// Splits the elements of a tuple or array pattern into the ones before
+    /// its `..` and, if there is a `..`, the ones after it.
+    pub fn split_rest<'a>(
+        elems: &'a [Scrutinee<'sc>],
+    ) -> (&'a [Scrutinee<'sc>], Option<&'a [Scrutinee<'sc>]>) {
+        match elems
+            .iter()
+            .position(|elem| matches!(elem, Scrutinee::Rest { .. }))
+        {
+            Some(i) => (&elems[..i], Some(&elems[i + 1..])),
+            None => (elems, None),
+        }
+    }
+}
+
+impl<'sc> fmt::Display for Scrutinee<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Scrutinee::Literal { value, .. } => write!(f, "{}", value),
+            Scrutinee::VariableExpression { name, .. } => write!(f, "{}", name.name()),
+            Scrutinee::Tuple { elems, .. } => {
+                let elems = elems.iter().map(Scrutinee::to_string).collect::<Vec<_>>();
+                write!(f, "({})", elems.join(", "))
+            }
+            Scrutinee::Array { elems, .. } => {
+                let elems = elems.iter().map(Scrutinee::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", elems.join(", "))
+            }
+            Scrutinee::EnumVariant {
+                enum_name,
+                variant_name,
+                payload,
+                ..
+            } => {
+                write!(f, "{}::{}", enum_name.name(), variant_name.name())?;
+                if !payload.is_empty() {
+                    let payload = payload.iter().map(Scrutinee::to_string).collect::<Vec<_>>();
+                    write!(f, "({})", payload.join(", "))?;
+                }
+                Ok(())
+            }
+            Scrutinee::StructScrutinee {
+                struct_name,
+                fields,
+                rest,
+                ..
+            } => {
+                let mut fields = fields
+                    .iter()
+                    .map(StructScrutineeField::to_string)
+                    .collect::<Vec<_>>();
+                if *rest {
+                    fields.push("..".to_string());
+                }
+                write!(f, "{} {{ {} }}", struct_name.name(), fields.join(", "))
+            }
+            Scrutinee::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                if let Some(start) = start {
+                    write!(f, "{}", start)?;
+                }
+                write!(f, "{}", if *inclusive { "..=" } else { ".." })?;
+                match end {
+                    Some(end) => write!(f, "{}", end),
+                    None => Ok(()),
+                }
+            }
+            Scrutinee::Or { alternatives, .. } => {
+                let alternatives = alternatives
+                    .iter()
+                    .map(Scrutinee::to_string)
+                    .collect::<Vec<_>>();
+                write!(f, "{}", alternatives.join(" | "))
+            }
+            Scrutinee::Binding { name, sub, .. } => write!(f, "{} @ {}", name.name(), sub),
+            Scrutinee::Wildcard { .. } => write!(f, "_"),
+            Scrutinee::Rest { .. } => write!(f, ".."),
+        }
+    }
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub struct StructScrutineeField<'sc> {
+    pub field_name: Ident<'sc>,
     pub scrutinee: Scrutinee<'sc>,
 }
 
+impl<'sc> fmt::Display for StructScrutineeField<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.scrutinee {
+            // `Point { x }` is short for `Point { x: x }`
+            Scrutinee::VariableExpression { name, .. } if *name == self.field_name => {
+                write!(f, "{}", name.name())
+            }
+            scrutinee => write!(f, "{}: {}", self.field_name.name(), scrutinee),
+        }
+    }
+}
+
 pub mod constructors {
     use super::super::language::*;
 
@@ -257,9 +833,39 @@ pub mod constructors {
         is_mutable: bool,
     ) -> Node<'sc> {
         Node::Declaration(Declaration::VariableDeclaration(VariableDeclaration {
-            name: Ident { primary_name: name },
+            name: Ident::new(name, Span::dummy()),
             body,
             is_mutable,
+            span: Span::dummy(),
+        }))
+    }
+
+    pub fn destructuring_declaration<'sc>(
+        pattern: Scrutinee<'sc>,
+        body: Expression<'sc>,
+        is_mutable: bool,
+    ) -> Node<'sc> {
+        Node::Declaration(Declaration::DestructuringDeclaration(
+            DestructuringDeclaration {
+                pattern,
+                body,
+                is_mutable,
+                span: Span::dummy(),
+            },
+        ))
+    }
+
+    // the declaration of the `gensym`th identifier a `NameGenerator` handed out
+    pub fn generated_declaration<'sc>(
+        name: &'sc str,
+        gensym: usize,
+        body: Expression<'sc>,
+    ) -> Node<'sc> {
+        Node::Declaration(Declaration::VariableDeclaration(VariableDeclaration {
+            name: generated_ident(name, gensym),
+            body,
+            is_mutable: false,
+            span: Span::dummy(),
         }))
     }
 
@@ -268,25 +874,70 @@ pub mod constructors {
     }
 
     pub fn return_<'sc>(expr: Expression<'sc>) -> Node<'sc> {
-        Node::ReturnStatement(ReturnStatement { expr })
+        Node::ReturnStatement(ReturnStatement {
+            expr,
+            span: Span::dummy(),
+        })
+    }
+
+    pub fn while_loop<'sc>(condition: Expression<'sc>, body: Vec<Node<'sc>>) -> Node<'sc> {
+        Node::WhileLoop(WhileLoop {
+            condition,
+            body: CodeBlock { contents: body },
+            span: Span::dummy(),
+        })
     }
 
     pub fn reassignment<'sc>(lhs: Expression<'sc>, rhs: Expression<'sc>) -> Node<'sc> {
         Node::Declaration(Declaration::Reassignment(Reassignment {
             lhs: Box::new(lhs),
             rhs,
+            span: Span::dummy(),
         }))
     }
 
     pub fn match_<'sc>(primary: Expression<'sc>, branches: Vec<MatchBranch<'sc>>) -> Node<'sc> {
-        Node::MatchStatement(MatchStatement { primary, branches })
+        Node::MatchStatement(MatchStatement {
+            primary,
+            branches,
+            span: Span::dummy(),
+        })
+    }
+
+    pub fn match_expression<'sc>(
+        primary: Expression<'sc>,
+        branches: Vec<MatchBranch<'sc>>,
+    ) -> Expression<'sc> {
+        Expression::Match {
+            primary: Box::new(primary),
+            branches,
+            span: Span::dummy(),
+        }
     }
 
     pub fn match_branch<'sc>(
         condition: MatchScrutinee<'sc>,
         result: Expression<'sc>,
     ) -> MatchBranch<'sc> {
-        MatchBranch { condition, result }
+        MatchBranch {
+            condition,
+            guard: None,
+            result,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn guarded_match_branch<'sc>(
+        condition: MatchScrutinee<'sc>,
+        guard: Expression<'sc>,
+        result: Expression<'sc>,
+    ) -> MatchBranch<'sc> {
+        MatchBranch {
+            condition,
+            guard: Some(guard),
+            result,
+            span: Span::dummy(),
+        }
     }
 
     pub fn match_scrutinee<'sc>(scrutinee: Scrutinee<'sc>) -> MatchScrutinee<'sc> {
@@ -299,12 +950,31 @@ pub mod constructors {
 
     pub fn variable<'sc>(name: &'sc str) -> Expression<'sc> {
         Expression::VariableExpression {
-            name: Ident { primary_name: name },
+            name: Ident::new(name, Span::dummy()),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn generated_variable<'sc>(name: &'sc str, gensym: usize) -> Expression<'sc> {
+        Expression::VariableExpression {
+            name: generated_ident(name, gensym),
+            span: Span::dummy(),
+        }
+    }
+
+    fn generated_ident<'sc>(name: &'sc str, gensym: usize) -> Ident<'sc> {
+        Ident {
+            primary_name: name,
+            gensym: Some(gensym),
+            span: Span::dummy(),
         }
     }
 
     pub fn literal<'sc>(lit: Literal<'sc>) -> Expression<'sc> {
-        Expression::Literal { value: lit }
+        Expression::Literal {
+            value: lit,
+            span: Span::dummy(),
+        }
     }
 
     pub fn struct_<'sc>(
@@ -312,20 +982,87 @@ pub mod constructors {
         fields: Vec<StructExpressionField<'sc>>,
     ) -> Expression<'sc> {
         Expression::StructExpression {
-            struct_name: Ident { primary_name: name },
+            struct_name: Ident::new(name, Span::dummy()),
             fields,
+            span: Span::dummy(),
         }
     }
 
     pub fn struct_field<'sc>(name: &'sc str, value: Expression<'sc>) -> StructExpressionField<'sc> {
         StructExpressionField {
-            name: Ident { primary_name: name },
+            name: Ident::new(name, Span::dummy()),
             value,
         }
     }
 
     pub fn tuple<'sc>(elems: Vec<Expression<'sc>>) -> Expression<'sc> {
-        Expression::Tuple { elems }
+        Expression::Tuple {
+            elems,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn array<'sc>(contents: Vec<Expression<'sc>>) -> Expression<'sc> {
+        Expression::Array {
+            contents,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn tuple_index<'sc>(prefix: Expression<'sc>, index: usize) -> Expression<'sc> {
+        Expression::TupleIndex {
+            prefix: Box::new(prefix),
+            index,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn array_index<'sc>(prefix: Expression<'sc>, index: usize) -> Expression<'sc> {
+        Expression::ArrayIndex {
+            prefix: Box::new(prefix),
+            index,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn index_from_end<'sc>(prefix: Expression<'sc>, index: usize) -> Expression<'sc> {
+        Expression::IndexFromEnd {
+            prefix: Box::new(prefix),
+            index,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn length_check<'sc>(
+        prefix: Expression<'sc>,
+        length: usize,
+        at_least: bool,
+    ) -> Expression<'sc> {
+        Expression::LengthCheck {
+            prefix: Box::new(prefix),
+            length,
+            at_least,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn subfield<'sc>(prefix: Expression<'sc>, field_name: &'sc str) -> Expression<'sc> {
+        Expression::SubfieldExpression {
+            prefix: Box::new(prefix),
+            field_name: Ident::new(field_name, Span::dummy()),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn struct_kind_check<'sc>(
+        prefix: Expression<'sc>,
+        struct_name: &'sc str,
+    ) -> Expression<'sc> {
+        Expression::StructKindCheck {
+            prefix: Box::new(prefix),
+            struct_name: Ident::new(struct_name, Span::dummy()),
+            span: Span::dummy(),
+        }
     }
 
     pub fn boolean<'sc>(b: bool) -> Literal<'sc> {
@@ -335,6 +1072,7 @@ pub mod constructors {
     pub fn block<'sc>(nodes: Vec<Node<'sc>>) -> Expression<'sc> {
         Expression::CodeBlock {
             contents: CodeBlock { contents: nodes },
+            span: Span::dummy(),
         }
     }
 
@@ -343,6 +1081,25 @@ pub mod constructors {
             op2: Op2::And,
             left: Box::new(left),
             right: Box::new(right),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn binop_or<'sc>(left: Expression<'sc>, right: Expression<'sc>) -> Expression<'sc> {
+        Expression::BinOp {
+            op2: Op2::Or,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn binop<'sc>(op2: Op2, left: Expression<'sc>, right: Expression<'sc>) -> Expression<'sc> {
+        Expression::BinOp {
+            op2,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: Span::dummy(),
         }
     }
 
@@ -351,6 +1108,7 @@ pub mod constructors {
             op2: Op2::Eq,
             left: Box::new(left),
             right: Box::new(right),
+            span: Span::dummy(),
         }
     }
 
@@ -359,17 +1117,24 @@ pub mod constructors {
     }
 
     pub fn literal_scrutinee<'sc>(lit: Literal<'sc>) -> Scrutinee<'sc> {
-        Scrutinee::Literal { value: lit }
+        Scrutinee::Literal {
+            value: lit,
+            span: Span::dummy(),
+        }
     }
 
     pub fn variable_scrutinee<'sc>(name: &'sc str) -> Scrutinee<'sc> {
         Scrutinee::VariableExpression {
-            name: Ident { primary_name: name },
+            name: Ident::new(name, Span::dummy()),
+            span: Span::dummy(),
         }
     }
 
     pub fn tuple_scrutinee<'sc>(elems: Vec<Scrutinee<'sc>>) -> Scrutinee<'sc> {
-        Scrutinee::Tuple { elems }
+        Scrutinee::Tuple {
+            elems,
+            span: Span::dummy(),
+        }
     }
 
     pub fn struct_scrutinee<'sc>(
@@ -377,13 +1142,164 @@ pub mod constructors {
         fields: Vec<StructScrutineeField<'sc>>,
     ) -> Scrutinee<'sc> {
         Scrutinee::StructScrutinee {
-            struct_name: Ident { primary_name: name },
+            struct_name: Ident::new(name, Span::dummy()),
+            fields,
+            rest: false,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn enum_declaration<'sc>(
+        name: &'sc str,
+        variants: Vec<(&'sc str, Vec<&'sc str>)>,
+    ) -> Node<'sc> {
+        let variants = variants
+            .into_iter()
+            .map(|(name, payload)| EnumVariant {
+                name: Ident::new(name, Span::dummy()),
+                payload: payload
+                    .into_iter()
+                    .map(|type_name| Ident::new(type_name, Span::dummy()))
+                    .collect(),
+            })
+            .collect();
+        Node::Declaration(Declaration::EnumDeclaration(EnumDeclaration {
+            name: Ident::new(name, Span::dummy()),
+            variants,
+            span: Span::dummy(),
+        }))
+    }
+
+    pub fn struct_declaration<'sc>(name: &'sc str, fields: Vec<(&'sc str, &'sc str)>) -> Node<'sc> {
+        let fields = fields
+            .into_iter()
+            .map(|(name, type_name)| StructField {
+                name: Ident::new(name, Span::dummy()),
+                r#type: Ident::new(type_name, Span::dummy()),
+            })
+            .collect();
+        Node::Declaration(Declaration::StructDeclaration(StructDeclaration {
+            name: Ident::new(name, Span::dummy()),
+            fields,
+            span: Span::dummy(),
+        }))
+    }
+
+    pub fn enum_instantiation<'sc>(
+        enum_name: &'sc str,
+        variant_name: &'sc str,
+        payload: Vec<Expression<'sc>>,
+    ) -> Expression<'sc> {
+        Expression::EnumInstantiation {
+            enum_name: Ident::new(enum_name, Span::dummy()),
+            variant_name: Ident::new(variant_name, Span::dummy()),
+            payload,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn variant_check<'sc>(
+        prefix: Expression<'sc>,
+        enum_name: &'sc str,
+        variant_name: &'sc str,
+    ) -> Expression<'sc> {
+        Expression::VariantCheck {
+            prefix: Box::new(prefix),
+            enum_name: Ident::new(enum_name, Span::dummy()),
+            variant_name: Ident::new(variant_name, Span::dummy()),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn variant_payload<'sc>(prefix: Expression<'sc>, index: usize) -> Expression<'sc> {
+        Expression::VariantPayload {
+            prefix: Box::new(prefix),
+            index,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn enum_scrutinee<'sc>(
+        enum_name: &'sc str,
+        variant_name: &'sc str,
+        payload: Vec<Scrutinee<'sc>>,
+    ) -> Scrutinee<'sc> {
+        Scrutinee::EnumVariant {
+            enum_name: Ident::new(enum_name, Span::dummy()),
+            variant_name: Ident::new(variant_name, Span::dummy()),
+            payload,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn array_scrutinee<'sc>(elems: Vec<Scrutinee<'sc>>) -> Scrutinee<'sc> {
+        Scrutinee::Array {
+            elems,
+            span: Span::dummy(),
+        }
+    }
+
+    // `name { fields, .. }`
+    pub fn struct_scrutinee_with_rest<'sc>(
+        name: &'sc str,
+        fields: Vec<StructScrutineeField<'sc>>,
+    ) -> Scrutinee<'sc> {
+        Scrutinee::StructScrutinee {
+            struct_name: Ident::new(name, Span::dummy()),
             fields,
+            rest: true,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn range_scrutinee<'sc>(
+        start: Option<Literal<'sc>>,
+        end: Option<Literal<'sc>>,
+        inclusive: bool,
+    ) -> Scrutinee<'sc> {
+        Scrutinee::Range {
+            start,
+            end,
+            inclusive,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn or_scrutinee<'sc>(alternatives: Vec<Scrutinee<'sc>>) -> Scrutinee<'sc> {
+        Scrutinee::Or {
+            alternatives,
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn binding_scrutinee<'sc>(name: &'sc str, sub: Scrutinee<'sc>) -> Scrutinee<'sc> {
+        Scrutinee::Binding {
+            name: Ident::new(name, Span::dummy()),
+            sub: Box::new(sub),
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn wildcard_scrutinee<'sc>() -> Scrutinee<'sc> {
+        Scrutinee::Wildcard {
+            span: Span::dummy(),
+        }
+    }
+
+    pub fn rest_scrutinee<'sc>() -> Scrutinee<'sc> {
+        Scrutinee::Rest {
+            span: Span::dummy(),
         }
     }
 
-    pub fn struct_scrutinee_field<'sc>(scrutinee: Scrutinee<'sc>) -> StructScrutineeField<'sc> {
-        StructScrutineeField { scrutinee }
+    pub fn struct_scrutinee_field<'sc>(
+        field_name: &'sc str,
+        scrutinee: Scrutinee<'sc>,
+    ) -> StructScrutineeField<'sc> {
+        StructScrutineeField {
+            field_name: Ident::new(field_name, Span::dummy()),
+            scrutinee,
+        }
     }
 
     pub fn if_statement<'sc>(
@@ -395,6 +1311,24 @@ pub mod constructors {
             primary,
             left,
             right,
+            span: Span::dummy(),
+        })
+    }
+
+    pub fn switch<'sc>(
+        primary: Expression<'sc>,
+        low: Literal<'sc>,
+        table: Vec<Option<usize>>,
+        blocks: Vec<Expression<'sc>>,
+        default: Option<Expression<'sc>>,
+    ) -> Node<'sc> {
+        Node::Switch(Switch {
+            primary,
+            low,
+            table,
+            blocks,
+            default,
+            span: Span::dummy(),
         })
     }
 
@@ -407,6 +1341,7 @@ pub mod constructors {
             condition: Box::new(primary),
             then: Box::new(left),
             r#else: right.map(Box::new),
+            span: Span::dummy(),
         }
     }
 }
@@ -0,0 +1,327 @@
+use std::fmt;
+
+use crate::language::Literal;
+use crate::parser::ParseError;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind<'sc> {
+    Ident(&'sc str),
+    Literal(Literal<'sc>),
+    // keywords
+    Let,
+    Mut,
+    While,
+    Return,
+    If,
+    Else,
+    Match,
+    Enum,
+    Struct,
+    // punctuation
+    OpenParen,
+    CloseParen,
+    OpenBrace,
+    CloseBrace,
+    OpenBracket,
+    CloseBracket,
+    Comma,
+    Semicolon,
+    Colon,
+    ColonColon,
+    Dot,
+    DotDot,
+    DotDotEq,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Assign,
+    EqEq,
+    AndAnd,
+    OrOr,
+    Pipe,
+    At,
+    FatArrow,
+    Eof,
+}
+
+impl<'sc> fmt::Display for TokenKind<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let text = match self {
+            TokenKind::Ident(name) => return write!(f, "`{}`", name),
+            TokenKind::Literal(lit) => return write!(f, "`{}`", lit),
+            TokenKind::Let => "let",
+            TokenKind::Mut => "mut",
+            TokenKind::While => "while",
+            TokenKind::Return => "return",
+            TokenKind::If => "if",
+            TokenKind::Else => "else",
+            TokenKind::Match => "match",
+            TokenKind::Enum => "enum",
+            TokenKind::Struct => "struct",
+            TokenKind::OpenParen => "(",
+            TokenKind::CloseParen => ")",
+            TokenKind::OpenBrace => "{",
+            TokenKind::CloseBrace => "}",
+            TokenKind::OpenBracket => "[",
+            TokenKind::CloseBracket => "]",
+            TokenKind::Comma => ",",
+            TokenKind::Semicolon => ";",
+            TokenKind::Colon => ":",
+            TokenKind::ColonColon => "::",
+            TokenKind::Dot => ".",
+            TokenKind::DotDot => "..",
+            TokenKind::Assign => "=",
+            TokenKind::EqEq => "==",
+            TokenKind::AndAnd => "&&",
+            TokenKind::OrOr => "||",
+            TokenKind::Pipe => "|",
+            TokenKind::DotDotEq => "..=",
+            TokenKind::Lt => "<",
+            TokenKind::Le => "<=",
+            TokenKind::Gt => ">",
+            TokenKind::Ge => ">=",
+            TokenKind::At => "@",
+            TokenKind::FatArrow => "=>",
+            TokenKind::Eof => return write!(f, "end of input"),
+        };
+        write!(f, "`{}`", text)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token<'sc> {
+    pub kind: TokenKind<'sc>,
+    // byte offset of the first character of the token
+    pub start: usize,
+    // byte offset one past the last character of the token
+    pub end: usize,
+}
+
+/// Splits `src` into tokens, always ending with a `TokenKind::Eof`.
+pub fn lex<'sc>(src: &'sc str) -> Result<Vec<Token<'sc>>, ParseError> {
+    let mut lexer = Lexer { src, pos: 0 };
+    let mut tokens = vec![];
+    loop {
+        let token = lexer.next_token()?;
+        let done = token.kind == TokenKind::Eof;
+        tokens.push(token);
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'sc> {
+    src: &'sc str,
+    pos: usize,
+}
+
+impl<'sc> Lexer<'sc> {
+    fn peek(&self) -> Option<char> {
+        self.src[self.pos..].chars().next()
+    }
+
+    fn peek_second(&self) -> Option<char> {
+        self.src[self.pos..].chars().nth(1)
+    }
+
+    fn skip_trivia(&mut self) {
+        loop {
+            match self.peek() {
+                Some(c) if c.is_whitespace() => self.pos += c.len_utf8(),
+                Some('/') if self.peek_second() == Some('/') => {
+                    while let Some(c) = self.peek() {
+                        if c == '\n' {
+                            break;
+                        }
+                        self.pos += c.len_utf8();
+                    }
+                }
+                _ => return,
+            }
+        }
+    }
+
+    fn take_while(&mut self, predicate: impl Fn(char) -> bool) -> &'sc str {
+        let start = self.pos;
+        while let Some(c) = self.peek() {
+            if !predicate(c) {
+                break;
+            }
+            self.pos += c.len_utf8();
+        }
+        &self.src[start..self.pos]
+    }
+
+    fn next_token(&mut self) -> Result<Token<'sc>, ParseError> {
+        self.skip_trivia();
+        let start = self.pos;
+        let c = match self.peek() {
+            Some(c) => c,
+            None => {
+                return Ok(Token {
+                    kind: TokenKind::Eof,
+                    start,
+                    end: start,
+                })
+            }
+        };
+        let kind = if c == 'b' && self.peek_second() == Some('\'') {
+            self.lex_byte()?
+        } else if c.is_alphabetic() || c == '_' {
+            let word = self.take_while(|c| c.is_alphanumeric() || c == '_');
+            match word {
+                "let" => TokenKind::Let,
+                "mut" => TokenKind::Mut,
+                "while" => TokenKind::While,
+                "return" => TokenKind::Return,
+                "if" => TokenKind::If,
+                "else" => TokenKind::Else,
+                "match" => TokenKind::Match,
+                "enum" => TokenKind::Enum,
+                "struct" => TokenKind::Struct,
+                "true" => TokenKind::Literal(Literal::Boolean(true)),
+                "false" => TokenKind::Literal(Literal::Boolean(false)),
+                word => TokenKind::Ident(word),
+            }
+        } else if c.is_ascii_digit() {
+            self.lex_integer()?
+        } else if c == '"' {
+            self.pos += 1;
+            let contents = self.take_while(|c| c != '"');
+            if self.peek() != Some('"') {
+                return Err(ParseError::new("Unterminated string literal", start));
+            }
+            self.pos += 1;
+            TokenKind::Literal(Literal::String(contents))
+        } else {
+            self.lex_punctuation()?
+        };
+        Ok(Token {
+            kind,
+            start,
+            end: self.pos,
+        })
+    }
+
+    // integers default to `u32`, other widths are picked with a suffix, e.g. `7u8`
+    fn lex_integer(&mut self) -> Result<TokenKind<'sc>, ParseError> {
+        let start = self.pos;
+        let digits = self.take_while(|c| c.is_ascii_digit() || c == '_');
+        let suffix = self.take_while(|c| c.is_alphanumeric());
+        let digits = digits.replace('_', "");
+        let overflow = || ParseError::new("Integer literal out of range", start);
+        let literal = match suffix {
+            "u8" => Literal::U8(digits.parse().map_err(|_| overflow())?),
+            "u16" => Literal::U16(digits.parse().map_err(|_| overflow())?),
+            "" | "u32" => Literal::U32(digits.parse().map_err(|_| overflow())?),
+            "u64" => Literal::U64(digits.parse().map_err(|_| overflow())?),
+            suffix => {
+                return Err(ParseError::new(
+                    format!("Unknown integer suffix `{}`", suffix),
+                    start,
+                ))
+            }
+        };
+        Ok(TokenKind::Literal(literal))
+    }
+
+    // byte literals are written `b'x'`
+    fn lex_byte(&mut self) -> Result<TokenKind<'sc>, ParseError> {
+        let start = self.pos;
+        self.pos += 2;
+        let byte = match self.peek() {
+            Some(c) if c.is_ascii() && c != '\'' => c as u8,
+            _ => return Err(ParseError::new("Invalid byte literal", start)),
+        };
+        self.pos += 1;
+        if self.peek() != Some('\'') {
+            return Err(ParseError::new("Unterminated byte literal", start));
+        }
+        self.pos += 1;
+        Ok(TokenKind::Literal(Literal::Byte(byte)))
+    }
+
+    fn lex_punctuation(&mut self) -> Result<TokenKind<'sc>, ParseError> {
+        let start = self.pos;
+        let two = |kind| (kind, 2);
+        let one = |kind| (kind, 1);
+        if self.src[self.pos..].starts_with("..=") {
+            self.pos += 3;
+            return Ok(TokenKind::DotDotEq);
+        }
+        let (kind, len) = match (self.peek(), self.peek_second()) {
+            (Some('='), Some('=')) => two(TokenKind::EqEq),
+            (Some('='), Some('>')) => two(TokenKind::FatArrow),
+            (Some('&'), Some('&')) => two(TokenKind::AndAnd),
+            (Some('|'), Some('|')) => two(TokenKind::OrOr),
+            (Some('.'), Some('.')) => two(TokenKind::DotDot),
+            (Some(':'), Some(':')) => two(TokenKind::ColonColon),
+            (Some('<'), Some('=')) => two(TokenKind::Le),
+            (Some('>'), Some('=')) => two(TokenKind::Ge),
+            (Some('<'), _) => one(TokenKind::Lt),
+            (Some('>'), _) => one(TokenKind::Gt),
+            (Some('='), _) => one(TokenKind::Assign),
+            (Some('|'), _) => one(TokenKind::Pipe),
+            (Some('@'), _) => one(TokenKind::At),
+            (Some('('), _) => one(TokenKind::OpenParen),
+            (Some(')'), _) => one(TokenKind::CloseParen),
+            (Some('{'), _) => one(TokenKind::OpenBrace),
+            (Some('}'), _) => one(TokenKind::CloseBrace),
+            (Some('['), _) => one(TokenKind::OpenBracket),
+            (Some(']'), _) => one(TokenKind::CloseBracket),
+            (Some(','), _) => one(TokenKind::Comma),
+            (Some(';'), _) => one(TokenKind::Semicolon),
+            (Some(':'), _) => one(TokenKind::Colon),
+            (Some('.'), _) => one(TokenKind::Dot),
+            (Some(c), _) => {
+                return Err(ParseError::new(
+                    format!("Unexpected character `{}`", c),
+                    start,
+                ))
+            }
+            (None, _) => unreachable!("punctuation is only lexed before the end of input"),
+        };
+        self.pos += len;
+        Ok(kind)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        language::Literal,
+        lexer::{lex, TokenKind},
+    };
+
+    #[test]
+    fn lex_tokens() {
+        let tokens = lex("let x = 5u8; // five\nx == b'a' && true")
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            tokens,
+            vec![
+                TokenKind::Let,
+                TokenKind::Ident("x"),
+                TokenKind::Assign,
+                TokenKind::Literal(Literal::U8(5)),
+                TokenKind::Semicolon,
+                TokenKind::Ident("x"),
+                TokenKind::EqEq,
+                TokenKind::Literal(Literal::Byte(b'a')),
+                TokenKind::AndAnd,
+                TokenKind::Literal(Literal::Boolean(true)),
+                TokenKind::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    fn lex_out_of_range() {
+        assert!(lex("256u8").is_err());
+    }
+}
@@ -0,0 +1,11 @@
+pub mod decision_tree;
+pub mod desugar;
+pub mod error;
+pub mod interpreter;
+pub mod language;
+pub mod lexer;
+pub mod matcher;
+pub mod parser;
+pub mod span;
+pub mod usefulness;
+pub mod value;
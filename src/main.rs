@@ -1,8 +1,34 @@
-mod desugar;
-mod interpreter;
-mod language;
-mod matcher;
+use std::{env, fs, process};
+
+use pattern_matcher::{interpreter::interpret, parser::parse};
 
 fn main() {
-    println!("Hello, world!");
+    let path = match env::args().nth(1) {
+        Some(path) => path,
+        None => {
+            eprintln!("usage: pattern-matcher <file>");
+            process::exit(1);
+        }
+    };
+    let src = match fs::read_to_string(&path) {
+        Ok(src) => src,
+        Err(err) => {
+            eprintln!("could not read {}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    let tree = match parse(&src) {
+        Ok(tree) => tree,
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    };
+    match interpret(tree) {
+        Ok(value) => println!("{}", value),
+        Err(err) => {
+            eprintln!("{}: {}", path, err);
+            process::exit(1);
+        }
+    }
 }
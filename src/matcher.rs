@@ -1,143 +1,755 @@
+use std::mem;
+
+use crate::error::MatchError;
 use crate::language::*;
+use crate::span::Span;
+use crate::value::Value;
+
+/// A test that has to pass at runtime for a pattern to match.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MatchReq<'sc> {
+    // if (x == y)
+    Eq(Expression<'sc>, Expression<'sc>),
+    // if x is a `struct_name`
+    StructKind(Expression<'sc>, Ident<'sc>),
+    // if x is the `variant_name` variant of `enum_name`
+    Variant {
+        exp: Expression<'sc>,
+        enum_name: Ident<'sc>,
+        variant_name: Ident<'sc>,
+    },
+    // if x has `length` elements, or at least that many
+    Length {
+        exp: Expression<'sc>,
+        length: usize,
+        at_least: bool,
+    },
+    // if start <= x, and x < end (or x <= end)
+    Range {
+        exp: Expression<'sc>,
+        start: Option<Literal<'sc>>,
+        end: Option<Literal<'sc>>,
+        inclusive: bool,
+    },
+    // if any of the alternatives hold
+    Any(Vec<MatchReqMap<'sc>>),
+}
+
+impl<'sc> MatchReq<'sc> {
+    /// The runtime test as an expression, with `span` pointing at the code it
+    /// was generated for.
+    pub fn into_expression(self, span: Span<'sc>) -> Expression<'sc> {
+        match self {
+            MatchReq::Eq(left, right) => Expression::BinOp {
+                op2: Op2::Eq,
+                left: Box::new(left),
+                right: Box::new(right),
+                span,
+            },
+            MatchReq::StructKind(exp, struct_name) => Expression::StructKindCheck {
+                prefix: Box::new(exp),
+                struct_name,
+                span,
+            },
+            MatchReq::Variant {
+                exp,
+                enum_name,
+                variant_name,
+            } => Expression::VariantCheck {
+                prefix: Box::new(exp),
+                enum_name,
+                variant_name,
+                span,
+            },
+            MatchReq::Length {
+                exp,
+                length,
+                at_least,
+            } => Expression::LengthCheck {
+                prefix: Box::new(exp),
+                length,
+                at_least,
+                span,
+            },
+            MatchReq::Range {
+                exp,
+                start,
+                end,
+                inclusive,
+            } => {
+                let bound = |value| Box::new(Expression::Literal { value, span });
+                let low = start.map(|start| Expression::BinOp {
+                    op2: Op2::Le,
+                    left: bound(start),
+                    right: Box::new(exp.clone()),
+                    span,
+                });
+                let high = end.map(|end| Expression::BinOp {
+                    op2: if inclusive { Op2::Le } else { Op2::Lt },
+                    left: Box::new(exp.clone()),
+                    right: bound(end),
+                    span,
+                });
+                match (low, high) {
+                    (Some(low), Some(high)) => Expression::BinOp {
+                        op2: Op2::And,
+                        left: Box::new(low),
+                        right: Box::new(high),
+                        span,
+                    },
+                    (Some(condition), None) | (None, Some(condition)) => condition,
+                    (None, None) => Expression::Literal {
+                        value: Literal::Boolean(true),
+                        span,
+                    },
+                }
+            }
+            MatchReq::Any(alternatives) => alternatives
+                .into_iter()
+                .map(|alternative| {
+                    conjunction(alternative, span).unwrap_or(Expression::Literal {
+                        value: Literal::Boolean(true),
+                        span,
+                    })
+                })
+                .fold(None, |disjunction, condition| match disjunction {
+                    None => Some(condition),
+                    Some(disjunction) => Some(Expression::BinOp {
+                        op2: Op2::Or,
+                        left: Box::new(disjunction),
+                        right: Box::new(condition),
+                        span,
+                    }),
+                })
+                .unwrap_or(Expression::Literal {
+                    value: Literal::Boolean(false),
+                    span,
+                }),
+        }
+    }
+}
+
+/// All of `match_req_map` joined with `&&`, or `None` if there is nothing to
+/// test.
+pub fn conjunction<'sc>(
+    match_req_map: MatchReqMap<'sc>,
+    span: Span<'sc>,
+) -> Option<Expression<'sc>> {
+    match_req_map
+        .into_iter()
+        .map(|match_req| match_req.into_expression(span))
+        .fold(None, |conjunction, condition| match conjunction {
+            None => Some(condition),
+            Some(conjunction) => Some(Expression::BinOp {
+                op2: Op2::And,
+                left: Box::new(conjunction),
+                right: Box::new(condition),
+                span,
+            }),
+        })
+}
 
-// if (x == y)
-pub type MatchReqMap<'sc> = Vec<(Expression<'sc>, Expression<'sc>)>;
+pub type MatchReqMap<'sc> = Vec<MatchReq<'sc>>;
 // let z = 4;
 pub type MatchImplMap<'sc> = Vec<(&'sc str, Expression<'sc>)>;
 
+pub type MatchResult<'sc> = Result<(MatchReqMap<'sc>, MatchImplMap<'sc>), MatchError<'sc>>;
+
 pub fn matcher<'sc>(
     exp: &Expression<'sc>,
     scrutinee: &Scrutinee<'sc>,
     namespace: &Namespace<'sc>,
-) -> Option<(MatchReqMap<'sc>, MatchImplMap<'sc>)> {
-    let exp = eval_exp(exp, namespace);
+) -> MatchResult<'sc> {
+    let value = eval_exp(exp, namespace)?;
+    match_value(&value, scrutinee)
+}
+
+/// Matches `scrutinee` against an expression whose value isn't known until
+/// runtime, e.g. a function parameter. Instead of looking at a value, every
+/// sub-pattern is matched against a projection out of `exp`, guarded by the
+/// tests that make that projection valid.
+pub fn symbolic_matcher<'sc>(
+    exp: &Expression<'sc>,
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    match scrutinee {
+        Scrutinee::Literal { value, span } => {
+            let match_req_map = vec![MatchReq::Eq(
+                Expression::Literal {
+                    value: value.clone(),
+                    span: *span,
+                },
+                exp.clone(),
+            )];
+            Ok((match_req_map, vec![]))
+        }
+        Scrutinee::VariableExpression { name, .. } => {
+            Ok((vec![], vec![(name.primary_name, exp.clone())]))
+        }
+        Scrutinee::Range {
+            start,
+            end,
+            inclusive,
+            ..
+        } => {
+            let match_req_map = vec![MatchReq::Range {
+                exp: exp.clone(),
+                start: start.clone(),
+                end: end.clone(),
+                inclusive: *inclusive,
+            }];
+            Ok((match_req_map, vec![]))
+        }
+        Scrutinee::Tuple { elems, span } => {
+            symbolic_sequence(exp, elems, *span, |prefix, index, span| {
+                Expression::TupleIndex {
+                    prefix,
+                    index,
+                    span,
+                }
+            })
+        }
+        Scrutinee::Array { elems, span } => {
+            symbolic_sequence(exp, elems, *span, |prefix, index, span| {
+                Expression::ArrayIndex {
+                    prefix,
+                    index,
+                    span,
+                }
+            })
+        }
+        Scrutinee::StructScrutinee {
+            struct_name,
+            fields,
+            span,
+            ..
+        } => {
+            // the kind check comes first so that the projections below are only
+            // evaluated on the right kind of struct
+            let mut match_req_maps = vec![MatchReq::StructKind(exp.clone(), struct_name.clone())];
+            let mut match_impl_maps = vec![];
+            for field in fields.iter() {
+                let projection = Expression::SubfieldExpression {
+                    prefix: Box::new(exp.clone()),
+                    field_name: field.field_name.clone(),
+                    span: *span,
+                };
+                let (mut match_req_map, mut match_impl_map) =
+                    symbolic_matcher(&projection, &field.scrutinee)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
+            }
+            Ok((match_req_maps, match_impl_maps))
+        }
+        Scrutinee::EnumVariant {
+            enum_name,
+            variant_name,
+            payload,
+            span,
+        } => {
+            // as with structs, the payload is only projected out once the
+            // variant is known
+            let mut match_req_maps = vec![MatchReq::Variant {
+                exp: exp.clone(),
+                enum_name: enum_name.clone(),
+                variant_name: variant_name.clone(),
+            }];
+            let mut match_impl_maps = vec![];
+            for (index, elem) in payload.iter().enumerate() {
+                let projection = Expression::VariantPayload {
+                    prefix: Box::new(exp.clone()),
+                    index,
+                    span: *span,
+                };
+                let (mut match_req_map, mut match_impl_map) = symbolic_matcher(&projection, elem)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
+            }
+            Ok((match_req_maps, match_impl_maps))
+        }
+        Scrutinee::Or { alternatives, span } => {
+            match_alternatives(alternatives, *span, |alternative| {
+                symbolic_matcher(exp, alternative)
+            })
+        }
+        Scrutinee::Binding { name, sub, .. } => {
+            let (match_req_map, mut match_impl_map) = symbolic_matcher(exp, sub)?;
+            match_impl_map.insert(0, (name.primary_name, exp.clone()));
+            Ok((match_req_map, match_impl_map))
+        }
+        Scrutinee::Wildcard { .. } => Ok((vec![], vec![])),
+        Scrutinee::Rest { span } => Err(MatchError::MisplacedRest { span: *span }),
+    }
+}
+
+// The elements of a tuple or array pattern, each matched against a
+// projection out of `exp` made by `index`, after a check that `exp` has
+// enough elements for all of them.
+fn symbolic_sequence<'sc>(
+    exp: &Expression<'sc>,
+    elems: &[Scrutinee<'sc>],
+    span: Span<'sc>,
+    index: impl Fn(Box<Expression<'sc>>, usize, Span<'sc>) -> Expression<'sc>,
+) -> MatchResult<'sc> {
+    let (before, after) = Scrutinee::split_rest(elems);
+    let after = after.unwrap_or(&[]);
+    // elements after a `..` are counted from the end, since the length isn't
+    // known here
+    let projections = before
+        .iter()
+        .enumerate()
+        .map(|(i, elem)| (index(Box::new(exp.clone()), i, span), elem))
+        .chain(after.iter().rev().enumerate().map(|(i, elem)| {
+            let projection = Expression::IndexFromEnd {
+                prefix: Box::new(exp.clone()),
+                index: i,
+                span,
+            };
+            (projection, elem)
+        }))
+        .collect::<Vec<_>>();
+    let mut match_req_maps = vec![MatchReq::Length {
+        exp: exp.clone(),
+        length: before.len() + after.len(),
+        at_least: before.len() != elems.len(),
+    }];
+    let mut match_impl_maps = vec![];
+    for (projection, elem) in projections.iter() {
+        let (mut match_req_map, mut match_impl_map) = symbolic_matcher(projection, elem)?;
+        match_req_maps.append(&mut match_req_map);
+        match_impl_maps.append(&mut match_impl_map);
+    }
+    Ok((match_req_maps, match_impl_maps))
+}
+
+fn match_value<'sc>(value: &Value<'sc>, scrutinee: &Scrutinee<'sc>) -> MatchResult<'sc> {
     match scrutinee {
-        Scrutinee::Literal { value: n } => match_literal(&exp, n),
-        Scrutinee::VariableExpression { name } => {
+        Scrutinee::Literal { value: n, span } => match_literal(value, n, *span, scrutinee),
+        Scrutinee::VariableExpression { name, .. } => {
             let match_req_map = vec![];
-            let match_impl_map = vec![(name.primary_name, exp.clone())];
-            Some((match_req_map, match_impl_map))
+            let match_impl_map = vec![(name.primary_name, Expression::from(value.clone()))];
+            Ok((match_req_map, match_impl_map))
         }
-        Scrutinee::Tuple { elems } => match_tuple(&exp, elems, namespace),
+        Scrutinee::Range {
+            start,
+            end,
+            inclusive,
+            ..
+        } => {
+            // the bounds are the same width, and so has the value to be
+            let same_width = start.iter().chain(end.iter()).next().map(|bound| {
+                mem::discriminant(value) == mem::discriminant(&Value::from(bound.clone()))
+            });
+            if same_width != Some(true) {
+                return incompatible(value, scrutinee);
+            }
+            let match_req_map = vec![MatchReq::Range {
+                exp: Expression::from(value.clone()),
+                start: start.clone(),
+                end: end.clone(),
+                inclusive: *inclusive,
+            }];
+            Ok((match_req_map, vec![]))
+        }
+        Scrutinee::Tuple { elems, .. } => match_tuple(value, elems, scrutinee),
+        Scrutinee::Array { elems, .. } => match_array(value, elems, scrutinee),
         Scrutinee::StructScrutinee {
             struct_name,
             fields,
-        } => match_struct(&exp, struct_name, fields, namespace),
-        x => unimplemented!(),
+            rest,
+            ..
+        } => match_struct(value, struct_name, fields, *rest, scrutinee),
+        Scrutinee::EnumVariant {
+            enum_name,
+            variant_name,
+            payload,
+            ..
+        } => match_variant(value, enum_name, variant_name, payload, scrutinee),
+        Scrutinee::Or { alternatives, span } => {
+            match_alternatives(alternatives, *span, |alternative| {
+                match_value(value, alternative)
+            })
+        }
+        Scrutinee::Binding { name, sub, .. } => {
+            let (match_req_map, mut match_impl_map) = match_value(value, sub)?;
+            match_impl_map.insert(0, (name.primary_name, Expression::from(value.clone())));
+            Ok((match_req_map, match_impl_map))
+        }
+        Scrutinee::Wildcard { .. } => Ok((vec![], vec![])),
+        Scrutinee::Rest { span } => Err(MatchError::MisplacedRest { span: *span }),
+    }
+}
+
+// An or-pattern matches when any of its alternatives do. Each variable is
+// bound to whatever it projects in the first alternative that matched, which
+// takes an `if` unless every alternative projects the same thing.
+fn match_alternatives<'sc>(
+    alternatives: &[Scrutinee<'sc>],
+    span: Span<'sc>,
+    mut match_alternative: impl FnMut(&Scrutinee<'sc>) -> MatchResult<'sc>,
+) -> MatchResult<'sc> {
+    check_or_bindings(alternatives)?;
+    let mut matched = vec![];
+    for alternative in alternatives.iter() {
+        matched.push(match_alternative(alternative)?);
     }
+    let names = match matched.first() {
+        Some((_, match_impl_map)) => match_impl_map
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>(),
+        None => vec![],
+    };
+    let mut match_impl_maps = vec![];
+    for name in names.into_iter() {
+        let projections = matched
+            .iter()
+            .map(|(match_req_map, match_impl_map)| {
+                let (_, projection) = match_impl_map
+                    .iter()
+                    .find(|(other, _)| *other == name)
+                    .expect("every alternative binds the same variables");
+                (match_req_map, projection)
+            })
+            .collect::<Vec<_>>();
+        let (_, first) = projections[0];
+        if projections
+            .iter()
+            .all(|(_, projection)| *projection == first)
+        {
+            match_impl_maps.push((name, first.clone()));
+            continue;
+        }
+        // built from the last alternative back, which needs no test since
+        // one of the alternatives is known to have matched
+        let mut binding = None;
+        for (match_req_map, projection) in projections.into_iter().rev() {
+            binding = Some(match (binding, conjunction(match_req_map.clone(), span)) {
+                (Some(r#else), Some(condition)) => Expression::IfExp {
+                    condition: Box::new(condition),
+                    then: Box::new(projection.clone()),
+                    r#else: Some(Box::new(r#else)),
+                    span,
+                },
+                _ => projection.clone(),
+            });
+        }
+        match_impl_maps.push((name, binding.expect("an or-pattern has alternatives")));
+    }
+    let match_req_maps = vec![MatchReq::Any(
+        matched
+            .into_iter()
+            .map(|(match_req_map, _)| match_req_map)
+            .collect(),
+    )];
+    Ok((match_req_maps, match_impl_maps))
+}
+
+fn check_or_bindings<'sc>(alternatives: &[Scrutinee<'sc>]) -> Result<(), MatchError<'sc>> {
+    let first = match alternatives.first() {
+        Some(first) => first.bindings(),
+        None => return Ok(()),
+    };
+    for alternative in alternatives[1..].iter() {
+        let bindings = alternative.bindings();
+        let missing = first
+            .iter()
+            .find(|name| !bindings.iter().any(|other| other.name() == name.name()))
+            .map(|name| (*name, alternative.span()))
+            .or_else(|| {
+                bindings
+                    .iter()
+                    .find(|name| !first.iter().any(|other| other.name() == name.name()))
+                    .map(|name| (*name, alternatives[0].span()))
+            });
+        if let Some((name, span)) = missing {
+            return Err(MatchError::InconsistentOrBindings {
+                name: name.clone(),
+                span,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn incompatible<'sc, T>(
+    value: &Value<'sc>,
+    scrutinee: &Scrutinee<'sc>,
+) -> Result<T, MatchError<'sc>> {
+    Err(MatchError::IncompatiblePattern {
+        scrutinee: Box::new(scrutinee.clone()),
+        exp: Box::new(Expression::from(value.clone())),
+    })
 }
 
 fn match_literal<'sc>(
-    exp: &Expression<'sc>,
+    value: &Value<'sc>,
     n: &Literal<'sc>,
-) -> Option<(MatchReqMap<'sc>, MatchImplMap<'sc>)> {
-    match exp {
-        Expression::Literal { value: m } => {
-            let match_req_map = vec![(
-                Expression::Literal { value: n.clone() },
-                Expression::Literal { value: m.clone() },
-            )];
-            let match_impl_map = vec![];
-            Some((match_req_map, match_impl_map))
-        }
-        _ => None,
+    span: Span<'sc>,
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    // a `u8` pattern can never match a `u32`, and so on
+    if mem::discriminant(value) != mem::discriminant(&Value::from(n.clone())) {
+        return incompatible(value, scrutinee);
     }
+    let match_req_map = vec![MatchReq::Eq(
+        Expression::Literal {
+            value: n.clone(),
+            span,
+        },
+        Expression::from(value.clone()),
+    )];
+    let match_impl_map = vec![];
+    Ok((match_req_map, match_impl_map))
 }
 
 fn match_tuple<'sc>(
-    exp: &Expression<'sc>,
-    scrutinee_elems: &Vec<Scrutinee<'sc>>,
-    namespace: &Namespace<'sc>,
-) -> Option<(MatchReqMap<'sc>, MatchImplMap<'sc>)> {
-    match exp {
-        Expression::Tuple { elems } => {
-            if elems.len() != scrutinee_elems.len() {
-                return None;
+    value: &Value<'sc>,
+    scrutinee_elems: &[Scrutinee<'sc>],
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    match value {
+        Value::Tuple(elems) => {
+            let (before, after) = Scrutinee::split_rest(scrutinee_elems);
+            let expected = before.len() + after.map_or(0, <[_]>::len);
+            let arity_matches = match after {
+                None => elems.len() == expected,
+                Some(_) => elems.len() >= expected,
+            };
+            if !arity_matches {
+                return Err(MatchError::TupleArityMismatch {
+                    scrutinee: Box::new(scrutinee.clone()),
+                    exp: Box::new(Expression::from(value.clone())),
+                    expected,
+                    found: elems.len(),
+                });
             }
+            // the elements after a `..` line up with the end of the tuple
+            let after = after.unwrap_or(&[]);
+            let pairs = elems
+                .iter()
+                .zip(before.iter())
+                .chain(elems[elems.len() - after.len()..].iter().zip(after.iter()));
             let mut match_req_maps = vec![];
             let mut match_impl_maps = vec![];
-            for (elem, scrutinee_elem) in elems.iter().zip(scrutinee_elems.iter()) {
-                match matcher(elem, scrutinee_elem, namespace) {
-                    Some((mut match_req_map, mut match_impl_map)) => {
-                        match_req_maps.append(&mut match_req_map);
-                        match_impl_maps.append(&mut match_impl_map);
-                    }
-                    None => return None,
-                }
+            for (elem, scrutinee_elem) in pairs {
+                let (mut match_req_map, mut match_impl_map) = match_value(elem, scrutinee_elem)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
+            }
+            Ok((match_req_maps, match_impl_maps))
+        }
+        _ => incompatible(value, scrutinee),
+    }
+}
+
+fn match_array<'sc>(
+    value: &Value<'sc>,
+    scrutinee_elems: &[Scrutinee<'sc>],
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    match value {
+        Value::Array(elems) => {
+            let (before, after) = Scrutinee::split_rest(scrutinee_elems);
+            let expected = before.len() + after.map_or(0, <[_]>::len);
+            let length_matches = match after {
+                None => elems.len() == expected,
+                Some(_) => elems.len() >= expected,
+            };
+            // unlike the arity of a tuple, the length of an array is no reason
+            // to reject the pattern, it just doesn't match. Matching it like
+            // a runtime value gives a length check that fails and bindings
+            // that are never evaluated.
+            if !length_matches {
+                return symbolic_matcher(&Expression::from(value.clone()), scrutinee);
             }
-            Some((match_req_maps, match_impl_maps))
+            let after = after.unwrap_or(&[]);
+            let pairs = elems
+                .iter()
+                .zip(before.iter())
+                .chain(elems[elems.len() - after.len()..].iter().zip(after.iter()));
+            let mut match_req_maps = vec![];
+            let mut match_impl_maps = vec![];
+            for (elem, scrutinee_elem) in pairs {
+                let (mut match_req_map, mut match_impl_map) = match_value(elem, scrutinee_elem)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
+            }
+            Ok((match_req_maps, match_impl_maps))
         }
-        _ => None,
+        _ => incompatible(value, scrutinee),
     }
 }
 
 fn match_struct<'sc>(
-    exp: &Expression<'sc>,
+    value: &Value<'sc>,
     scrutinee_struct_name: &Ident<'sc>,
-    scrutinee_fields: &Vec<StructScrutineeField<'sc>>,
-    namespace: &Namespace<'sc>,
-) -> Option<(MatchReqMap<'sc>, MatchImplMap<'sc>)> {
-    match exp {
-        Expression::StructExpression {
+    scrutinee_fields: &[StructScrutineeField<'sc>],
+    rest: bool,
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    match value {
+        Value::Struct {
             struct_name,
             fields,
         } => {
-            if struct_name.primary_name != scrutinee_struct_name.primary_name {
-                return None;
+            // like another variant of an enum, another struct just doesn't
+            // match, which is left to a check that fails at runtime
+            if *struct_name != scrutinee_struct_name.primary_name {
+                return symbolic_matcher(&Expression::from(value.clone()), scrutinee);
             }
             let mut match_req_maps = vec![];
             let mut match_impl_maps = vec![];
-            for (field, scrutinee_field) in fields.iter().zip(scrutinee_fields.iter()) {
-                let scrutinee = scrutinee_field.scrutinee.clone();
-                match scrutinee {
-                    // if the scrutinee is simply naming the struct field ...
-                    Scrutinee::VariableExpression { name } => {
-                        if field.name.primary_name != name.primary_name {
-                            return None;
-                        }
-                        match_impl_maps
-                            .push((name.primary_name, eval_exp(&field.value, namespace)));
+            for scrutinee_field in scrutinee_fields.iter() {
+                let field_value = match fields
+                    .iter()
+                    .find(|(name, _)| *name == scrutinee_field.field_name.primary_name)
+                {
+                    Some((_, field_value)) => field_value,
+                    None => {
+                        return Err(MatchError::UnknownField {
+                            scrutinee: Box::new(scrutinee.clone()),
+                            exp: Box::new(Expression::from(value.clone())),
+                            field: scrutinee_field.field_name.clone(),
+                        })
                     }
-                    // or if the scrutinee has a more complex agenda
-                    scrutinee => match matcher(&field.value, &scrutinee.clone(), namespace) {
-                        Some((mut match_req_map, mut match_impl_map)) => {
-                            match_req_maps.append(&mut match_req_map);
-                            match_impl_maps.append(&mut match_impl_map);
-                        }
-                        None => return None,
-                    },
-                }
+                };
+                let (mut match_req_map, mut match_impl_map) =
+                    match_value(field_value, &scrutinee_field.scrutinee)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
+            }
+            // without a `..` every field has to be spelled out
+            let missing = fields.iter().find(|(name, _)| {
+                !scrutinee_fields
+                    .iter()
+                    .any(|field| field.field_name.primary_name == *name)
+            });
+            if let (Some((field, _)), false) = (missing, rest) {
+                return Err(MatchError::MissingField {
+                    scrutinee: Box::new(scrutinee.clone()),
+                    exp: Box::new(Expression::from(value.clone())),
+                    field,
+                });
+            }
+            Ok((match_req_maps, match_impl_maps))
+        }
+        _ => incompatible(value, scrutinee),
+    }
+}
+
+fn match_variant<'sc>(
+    value: &Value<'sc>,
+    scrutinee_enum_name: &Ident<'sc>,
+    scrutinee_variant_name: &Ident<'sc>,
+    scrutinee_payload: &[Scrutinee<'sc>],
+    scrutinee: &Scrutinee<'sc>,
+) -> MatchResult<'sc> {
+    match value {
+        Value::Enum {
+            enum_name,
+            variant_name,
+            payload,
+        } if *enum_name == scrutinee_enum_name.primary_name => {
+            // another variant of the same enum just doesn't match, which is
+            // left to a variant check that fails at runtime
+            if *variant_name != scrutinee_variant_name.primary_name {
+                return symbolic_matcher(&Expression::from(value.clone()), scrutinee);
+            }
+            if payload.len() != scrutinee_payload.len() {
+                return incompatible(value, scrutinee);
+            }
+            let mut match_req_maps = vec![];
+            let mut match_impl_maps = vec![];
+            for (elem, scrutinee_elem) in payload.iter().zip(scrutinee_payload.iter()) {
+                let (mut match_req_map, mut match_impl_map) = match_value(elem, scrutinee_elem)?;
+                match_req_maps.append(&mut match_req_map);
+                match_impl_maps.append(&mut match_impl_map);
             }
-            Some((match_req_maps, match_impl_maps))
+            Ok((match_req_maps, match_impl_maps))
         }
-        _ => None,
+        _ => incompatible(value, scrutinee),
     }
 }
 
-fn eval_exp<'sc>(exp: &Expression<'sc>, namespace: &Namespace<'sc>) -> Expression<'sc> {
+/// Evaluates `exp` as far as the namespace allows, failing on anything that
+/// isn't known until runtime.
+pub fn eval_exp<'sc>(
+    exp: &Expression<'sc>,
+    namespace: &Namespace<'sc>,
+) -> Result<Value<'sc>, MatchError<'sc>> {
     match exp {
-        Expression::Literal { value } => Expression::Literal {
-            value: value.clone(),
+        Expression::Literal { value, .. } => Ok(Value::from(value.clone())),
+        Expression::VariableExpression { name, .. } => match namespace.get(name.name().as_ref()) {
+            Some(value) => Ok(value.clone()),
+            None => Err(MatchError::UnboundVariable { name: name.clone() }),
         },
-        Expression::VariableExpression { name } => {
-            namespace.get(name.primary_name).unwrap().clone()
+        Expression::Unit { .. } => Ok(Value::Unit),
+        Expression::Array { contents, .. } => {
+            let mut values = vec![];
+            for elem in contents.iter() {
+                values.push(eval_exp(elem, namespace)?);
+            }
+            Ok(Value::Array(values))
         }
-        Expression::Tuple { elems } => Expression::Tuple {
-            elems: elems.clone(),
-        },
-        x => unimplemented!(),
+        Expression::Tuple { elems, .. } => {
+            let mut values = vec![];
+            for elem in elems.iter() {
+                values.push(eval_exp(elem, namespace)?);
+            }
+            Ok(Value::Tuple(values))
+        }
+        Expression::StructExpression {
+            struct_name,
+            fields,
+            ..
+        } => {
+            let mut values = vec![];
+            for field in fields.iter() {
+                values.push((field.name.primary_name, eval_exp(&field.value, namespace)?));
+            }
+            Ok(Value::Struct {
+                struct_name: struct_name.primary_name,
+                fields: values,
+            })
+        }
+        Expression::EnumInstantiation {
+            enum_name,
+            variant_name,
+            payload,
+            ..
+        } => {
+            let mut values = vec![];
+            for elem in payload.iter() {
+                values.push(eval_exp(elem, namespace)?);
+            }
+            Ok(Value::Enum {
+                enum_name: enum_name.primary_name,
+                variant_name: variant_name.primary_name,
+                payload: values,
+            })
+        }
+        exp => Err(MatchError::UnsupportedExpression {
+            exp: Box::new(exp.clone()),
+        }),
     }
 }
 
 #[cfg(test)]
 mod test {
-    use crate::{language::constructors::*, matcher::matcher};
+    use crate::{
+        error::MatchError,
+        language::{constructors::*, Literal, Namespace},
+        matcher::{conjunction, matcher, symbolic_matcher, MatchReq},
+        span::Span,
+        value::Value,
+    };
 
-    use std::collections::HashMap;
+    use std::convert::TryInto;
 
     #[test]
     fn match_u32() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = literal(u32_(4));
         let scrutinee = literal_scrutinee(u32_(4));
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -148,8 +760,8 @@ mod test {
 
     #[test]
     fn match_variable() {
-        let mut namespace = HashMap::new();
-        namespace.insert("x".to_string(), literal(u32_(4)));
+        let mut namespace = Namespace::default();
+        namespace.insert("x".to_string(), Value::U32(4));
         let exp = variable("x");
         let scrutinee = literal_scrutinee(u32_(4));
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -160,7 +772,7 @@ mod test {
 
     #[test]
     fn u32_to_variable() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = literal(u32_(4));
         let scrutinee = variable_scrutinee("x");
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -171,8 +783,8 @@ mod test {
 
     #[test]
     fn variable_to_variable() {
-        let mut namespace = HashMap::new();
-        namespace.insert("x".to_string(), literal(u32_(4)));
+        let mut namespace = Namespace::default();
+        namespace.insert("x".to_string(), Value::U32(4));
         let exp = variable("x");
         let scrutinee = variable_scrutinee("y");
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -183,7 +795,7 @@ mod test {
 
     #[test]
     fn tuple_to_variable() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = tuple(vec![literal(u32_(2)), literal(u32_(4))]);
         let scrutinee = variable_scrutinee("x");
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -194,7 +806,7 @@ mod test {
 
     #[test]
     fn tuple_to_tuple() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = tuple(vec![literal(u32_(2)), literal(u32_(4))]);
         let scrutinee =
             tuple_scrutinee(vec![literal_scrutinee(u32_(2)), literal_scrutinee(u32_(4))]);
@@ -206,7 +818,7 @@ mod test {
 
     #[test]
     fn tuple_to_tuple_variable() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = tuple(vec![literal(u32_(2)), literal(u32_(4))]);
         let scrutinee = tuple_scrutinee(vec![variable_scrutinee("x"), variable_scrutinee("y")]);
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -217,7 +829,7 @@ mod test {
 
     #[test]
     fn tuple_to_tuple_variable_u32() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = tuple(vec![literal(u32_(2)), literal(u32_(4))]);
         let scrutinee = tuple_scrutinee(vec![variable_scrutinee("x"), literal_scrutinee(u32_(4))]);
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -228,16 +840,23 @@ mod test {
 
     #[test]
     fn tuple_none() {
-        let namespace = HashMap::new();
+        let namespace = Namespace::default();
         let exp = tuple(vec![literal(u32_(2))]);
         let scrutinee = tuple_scrutinee(vec![variable_scrutinee("x"), literal_scrutinee(u32_(4))]);
         let matches = matcher(&exp, &scrutinee, &namespace);
-        assert_eq!(matches, None);
+        assert!(matches!(
+            matches,
+            Err(MatchError::TupleArityMismatch {
+                expected: 2,
+                found: 1,
+                ..
+            })
+        ));
     }
 
     #[test]
     fn struct_to_variable() {
-        let mut namespace = HashMap::new();
+        let mut namespace = Namespace::default();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -246,7 +865,9 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let exp = variable("foo");
         let scrutinee = variable_scrutinee("bar");
@@ -258,7 +879,7 @@ mod test {
 
     #[test]
     fn struct_to_struct() {
-        let mut namespace = HashMap::new();
+        let mut namespace = Namespace::default();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -267,14 +888,16 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let exp = variable("foo");
         let scrutinee = struct_scrutinee(
             "Point",
             vec![
-                struct_scrutinee_field(variable_scrutinee("x")),
-                struct_scrutinee_field(variable_scrutinee("y")),
+                struct_scrutinee_field("x", variable_scrutinee("x")),
+                struct_scrutinee_field("y", variable_scrutinee("y")),
             ],
         );
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -285,7 +908,7 @@ mod test {
 
     #[test]
     fn struct_to_struct_variable() {
-        let mut namespace = HashMap::new();
+        let mut namespace = Namespace::default();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -294,14 +917,16 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let exp = variable("foo");
         let scrutinee = struct_scrutinee(
             "Point",
             vec![
-                struct_scrutinee_field(variable_scrutinee("x")),
-                struct_scrutinee_field(literal_scrutinee(u32_(7))),
+                struct_scrutinee_field("x", variable_scrutinee("x")),
+                struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
             ],
         );
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -310,9 +935,34 @@ mod test {
         assert_eq!(match_req_map.len(), 1);
     }
 
+    #[test]
+    fn struct_fields_by_name() {
+        let namespace = Namespace::default();
+        let exp = struct_(
+            "Point",
+            vec![
+                struct_field("x", literal(u32_(5))),
+                struct_field("y", literal(u32_(7))),
+            ],
+        );
+        let scrutinee = struct_scrutinee(
+            "Point",
+            vec![
+                struct_scrutinee_field("y", variable_scrutinee("y")),
+                struct_scrutinee_field("x", literal_scrutinee(u32_(5))),
+            ],
+        );
+        let (match_req_map, match_impl_map) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![MatchReq::Eq(literal(u32_(5)), literal(u32_(5)))]
+        );
+        assert_eq!(match_impl_map, vec![("y", literal(u32_(7)))]);
+    }
+
     #[test]
     fn struct_none() {
-        let mut namespace = HashMap::new();
+        let mut namespace = Namespace::default();
         namespace.insert(
             "foo".to_string(),
             struct_(
@@ -321,14 +971,16 @@ mod test {
                     struct_field("x", literal(u32_(5))),
                     struct_field("y", literal(u32_(7))),
                 ],
-            ),
+            )
+            .try_into()
+            .unwrap(),
         );
         let exp = variable("foo");
         let scrutinee = struct_scrutinee(
             "Point",
             vec![
-                struct_scrutinee_field(variable_scrutinee("x")),
-                struct_scrutinee_field(literal_scrutinee(u32_(8))),
+                struct_scrutinee_field("x", variable_scrutinee("x")),
+                struct_scrutinee_field("y", literal_scrutinee(u32_(8))),
             ],
         );
         let matches = matcher(&exp, &scrutinee, &namespace);
@@ -336,4 +988,387 @@ mod test {
         assert_eq!(match_impl_map.len(), 1);
         assert_eq!(match_req_map.len(), 1);
     }
+
+    #[test]
+    fn unbound_variable() {
+        let namespace = Namespace::default();
+        let exp = variable("foo");
+        let scrutinee = variable_scrutinee("bar");
+        let matches = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(matches, Err(MatchError::UnboundVariable { .. })));
+    }
+
+    #[test]
+    fn literal_to_tuple() {
+        let namespace = Namespace::default();
+        let exp = literal(u32_(4));
+        let scrutinee = tuple_scrutinee(vec![variable_scrutinee("x")]);
+        let matches = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(
+            matches,
+            Err(MatchError::IncompatiblePattern { .. })
+        ));
+    }
+
+    #[test]
+    fn struct_unknown_field() {
+        let namespace = Namespace::default();
+        let exp = struct_("Point", vec![struct_field("x", literal(u32_(5)))]);
+        let scrutinee = struct_scrutinee(
+            "Point",
+            vec![struct_scrutinee_field("z", variable_scrutinee("z"))],
+        );
+        let matches = matcher(&exp, &scrutinee, &namespace);
+        match matches {
+            Err(MatchError::UnknownField { field, .. }) => assert_eq!(field.primary_name, "z"),
+            matches => panic!("expected an unknown field, found {:?}", matches),
+        }
+    }
+
+    #[test]
+    fn symbolic_tuple() {
+        let exp = variable("pair");
+        let scrutinee = tuple_scrutinee(vec![variable_scrutinee("x"), literal_scrutinee(u32_(4))]);
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![
+                MatchReq::Length {
+                    exp: variable("pair"),
+                    length: 2,
+                    at_least: false
+                },
+                MatchReq::Eq(literal(u32_(4)), tuple_index(variable("pair"), 1))
+            ]
+        );
+        assert_eq!(
+            match_impl_map,
+            vec![("x", tuple_index(variable("pair"), 0))]
+        );
+    }
+
+    #[test]
+    fn symbolic_tuple_rest() {
+        let exp = variable("triple");
+        let scrutinee = tuple_scrutinee(vec![
+            variable_scrutinee("first"),
+            rest_scrutinee(),
+            literal_scrutinee(u32_(4)),
+        ]);
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![
+                MatchReq::Length {
+                    exp: variable("triple"),
+                    length: 2,
+                    at_least: true
+                },
+                MatchReq::Eq(literal(u32_(4)), index_from_end(variable("triple"), 0))
+            ]
+        );
+        assert_eq!(
+            match_impl_map,
+            vec![("first", tuple_index(variable("triple"), 0))]
+        );
+    }
+
+    #[test]
+    fn tuple_rest() {
+        let namespace = Namespace::default();
+        let exp = tuple(vec![literal(u32_(1)), literal(u32_(2)), literal(u32_(3))]);
+        let scrutinee = tuple_scrutinee(vec![rest_scrutinee(), variable_scrutinee("last")]);
+        let (match_req_map, match_impl_map) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert!(match_req_map.is_empty());
+        assert_eq!(match_impl_map, vec![("last", literal(u32_(3)))]);
+
+        let scrutinee = tuple_scrutinee(vec![
+            variable_scrutinee("a"),
+            variable_scrutinee("b"),
+            rest_scrutinee(),
+            variable_scrutinee("c"),
+            variable_scrutinee("d"),
+        ]);
+        let matches = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(
+            matches,
+            Err(MatchError::TupleArityMismatch {
+                expected: 4,
+                found: 3,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn struct_rest() {
+        let namespace = Namespace::default();
+        let exp = struct_(
+            "Point",
+            vec![
+                struct_field("x", literal(u32_(5))),
+                struct_field("y", literal(u32_(7))),
+            ],
+        );
+        let fields = vec![struct_scrutinee_field("x", variable_scrutinee("x"))];
+        let scrutinee = struct_scrutinee_with_rest("Point", fields.clone());
+        let (_, match_impl_map) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(match_impl_map, vec![("x", literal(u32_(5)))]);
+
+        let scrutinee = struct_scrutinee("Point", fields);
+        let matches = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(
+            matches,
+            Err(MatchError::MissingField { field: "y", .. })
+        ));
+    }
+
+    #[test]
+    fn symbolic_struct() {
+        let exp = variable("foo");
+        let scrutinee = struct_scrutinee(
+            "Point",
+            vec![
+                struct_scrutinee_field("x", variable_scrutinee("x")),
+                struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
+            ],
+        );
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        assert!(matches!(match_req_map[0], MatchReq::StructKind(..)));
+        assert_eq!(
+            match_req_map[1],
+            MatchReq::Eq(literal(u32_(7)), subfield(variable("foo"), "y"))
+        );
+        assert_eq!(match_impl_map, vec![("x", subfield(variable("foo"), "x"))]);
+    }
+
+    #[test]
+    fn wildcard() {
+        let namespace = Namespace::default();
+        let exp = tuple(vec![literal(u32_(2)), literal(u32_(4))]);
+        let scrutinee = tuple_scrutinee(vec![wildcard_scrutinee(), literal_scrutinee(u32_(4))]);
+        let (match_req_map, match_impl_map) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(match_req_map.len(), 1);
+        assert!(match_impl_map.is_empty());
+        let (match_req_map, match_impl_map) =
+            symbolic_matcher(&variable("pair"), &scrutinee).unwrap();
+        // the length check and the literal
+        assert_eq!(match_req_map.len(), 2);
+        assert!(match_impl_map.is_empty());
+    }
+
+    #[test]
+    fn symbolic_or() {
+        let exp = variable("pair");
+        let scrutinee = or_scrutinee(vec![
+            tuple_scrutinee(vec![variable_scrutinee("x"), literal_scrutinee(u32_(0))]),
+            tuple_scrutinee(vec![literal_scrutinee(u32_(0)), variable_scrutinee("x")]),
+        ]);
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        let length = || MatchReq::Length {
+            exp: variable("pair"),
+            length: 2,
+            at_least: false,
+        };
+        assert_eq!(
+            match_req_map,
+            vec![MatchReq::Any(vec![
+                vec![
+                    length(),
+                    MatchReq::Eq(literal(u32_(0)), tuple_index(variable("pair"), 1))
+                ],
+                vec![
+                    length(),
+                    MatchReq::Eq(literal(u32_(0)), tuple_index(variable("pair"), 0))
+                ],
+            ])]
+        );
+        // `x` is whichever element the matching alternative picked out
+        let binding = if_expression(
+            binop_and(
+                length_check(variable("pair"), 2, false),
+                binop_eq(literal(u32_(0)), tuple_index(variable("pair"), 1)),
+            ),
+            tuple_index(variable("pair"), 0),
+            Some(tuple_index(variable("pair"), 1)),
+        );
+        assert_eq!(match_impl_map, vec![("x", binding)]);
+    }
+
+    #[test]
+    fn or_inconsistent_bindings() {
+        let namespace = Namespace::default();
+        let exp = tuple(vec![literal(u32_(1)), literal(u32_(2))]);
+        let scrutinee = or_scrutinee(vec![
+            tuple_scrutinee(vec![variable_scrutinee("x"), wildcard_scrutinee()]),
+            tuple_scrutinee(vec![wildcard_scrutinee(), variable_scrutinee("y")]),
+        ]);
+        let result = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(
+            result,
+            Err(MatchError::InconsistentOrBindings { name, .. }) if name.primary_name == "x"
+        ));
+    }
+
+    #[test]
+    fn symbolic_binding() {
+        let exp = variable("pair");
+        let scrutinee = binding_scrutinee(
+            "whole",
+            tuple_scrutinee(vec![variable_scrutinee("x"), literal_scrutinee(u32_(0))]),
+        );
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![
+                MatchReq::Length {
+                    exp: variable("pair"),
+                    length: 2,
+                    at_least: false
+                },
+                MatchReq::Eq(literal(u32_(0)), tuple_index(variable("pair"), 1))
+            ]
+        );
+        assert_eq!(
+            match_impl_map,
+            vec![
+                ("whole", variable("pair")),
+                ("x", tuple_index(variable("pair"), 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn range() {
+        let namespace = Namespace::default();
+        let exp = literal(u32_(7));
+        let scrutinee = range_scrutinee(Some(u32_(5)), Some(u32_(10)), false);
+        let (match_req_map, match_impl_map) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![MatchReq::Range {
+                exp: literal(u32_(7)),
+                start: Some(u32_(5)),
+                end: Some(u32_(10)),
+                inclusive: false
+            }]
+        );
+        assert_eq!(match_impl_map, vec![]);
+
+        // a `u32` range never matches a `u64`
+        let exp = literal(Literal::U64(7));
+        let result = matcher(&exp, &scrutinee, &namespace);
+        assert!(matches!(
+            result,
+            Err(MatchError::IncompatiblePattern { .. })
+        ));
+    }
+
+    #[test]
+    fn symbolic_array() {
+        let exp = variable("xs");
+        let scrutinee = array_scrutinee(vec![
+            variable_scrutinee("head"),
+            rest_scrutinee(),
+            variable_scrutinee("last"),
+        ]);
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![MatchReq::Length {
+                exp: variable("xs"),
+                length: 2,
+                at_least: true
+            }]
+        );
+        assert_eq!(
+            match_impl_map,
+            vec![
+                ("head", array_index(variable("xs"), 0)),
+                ("last", index_from_end(variable("xs"), 0))
+            ]
+        );
+    }
+
+    #[test]
+    fn array_too_short() {
+        // not an error, the pattern just doesn't match
+        let namespace = Namespace::default();
+        let exp = array(vec![literal(u32_(1))]);
+        let scrutinee = array_scrutinee(vec![variable_scrutinee("x"), variable_scrutinee("y")]);
+        let (match_req_map, _) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            match_req_map,
+            vec![MatchReq::Length {
+                exp: array(vec![literal(u32_(1))]),
+                length: 2,
+                at_least: false
+            }]
+        );
+    }
+
+    #[test]
+    fn symbolic_enum() {
+        let exp = variable("shape");
+        let scrutinee = enum_scrutinee(
+            "Shape",
+            "Rect",
+            vec![variable_scrutinee("w"), literal_scrutinee(u32_(1))],
+        );
+        let (match_req_map, match_impl_map) = symbolic_matcher(&exp, &scrutinee).unwrap();
+        // the payload is only looked at once the variant is known
+        assert_eq!(
+            conjunction(match_req_map, Span::dummy()),
+            Some(binop_and(
+                variant_check(variable("shape"), "Shape", "Rect"),
+                binop_eq(literal(u32_(1)), variant_payload(variable("shape"), 1)),
+            ))
+        );
+        assert_eq!(
+            match_impl_map,
+            vec![("w", variant_payload(variable("shape"), 0))]
+        );
+    }
+
+    #[test]
+    fn other_struct() {
+        // a value of another struct just fails the struct check, the same as
+        // one of another variant fails the variant check
+        let namespace = Namespace::default();
+        let exp = struct_("Point", vec![struct_field("x", literal(u32_(5)))]);
+        let scrutinee = struct_scrutinee(
+            "Line",
+            vec![struct_scrutinee_field("x", variable_scrutinee("x"))],
+        );
+        let (match_req_map, _) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            conjunction(match_req_map, Span::dummy()),
+            Some(struct_kind_check(exp.clone(), "Line"))
+        );
+
+        let exp = enum_instantiation("Shape", "Dot", vec![]);
+        let scrutinee = enum_scrutinee("Shape", "Circle", vec![variable_scrutinee("r")]);
+        let (match_req_map, _) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            conjunction(match_req_map, Span::dummy()),
+            Some(variant_check(exp.clone(), "Shape", "Circle"))
+        );
+    }
+
+    #[test]
+    fn other_variant() {
+        // a value of another variant just fails the variant check
+        let namespace = Namespace::default();
+        let exp = enum_instantiation("Shape", "Dot", vec![]);
+        let scrutinee = enum_scrutinee("Shape", "Circle", vec![variable_scrutinee("r")]);
+        let (match_req_map, _) = matcher(&exp, &scrutinee, &namespace).unwrap();
+        assert_eq!(
+            conjunction(match_req_map, Span::dummy()),
+            Some(variant_check(
+                enum_instantiation("Shape", "Dot", vec![]),
+                "Shape",
+                "Circle"
+            ))
+        );
+    }
 }
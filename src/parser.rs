@@ -0,0 +1,1273 @@
+use std::fmt;
+use std::mem;
+
+use crate::language::*;
+use crate::lexer::{lex, Token, TokenKind};
+use crate::span::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParseError {
+    pub message: String,
+    // byte offset into the source at which the error was detected
+    pub position: usize,
+}
+
+impl ParseError {
+    pub fn new(message: impl Into<String>, position: usize) -> Self {
+        ParseError {
+            message: message.into(),
+            position,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at byte {}", self.message, self.position)
+    }
+}
+
+/// Parses a whole program.
+///
+/// Every identifier, string literal and span in the resulting tree borrows
+/// from `src`.
+pub fn parse<'sc>(src: &'sc str) -> Result<Tree<'sc>, ParseError> {
+    let mut parser = Parser {
+        src,
+        tokens: lex(src)?,
+        pos: 0,
+    };
+    let nodes = parser.parse_nodes(&TokenKind::Eof)?;
+    Ok(Tree { nodes })
+}
+
+struct Parser<'sc> {
+    src: &'sc str,
+    tokens: Vec<Token<'sc>>,
+    pos: usize,
+}
+
+impl<'sc> Parser<'sc> {
+    fn peek(&self) -> &TokenKind<'sc> {
+        &self.tokens[self.pos].kind
+    }
+
+    fn peek_second(&self) -> &TokenKind<'sc> {
+        let pos = (self.pos + 1).min(self.tokens.len() - 1);
+        &self.tokens[pos].kind
+    }
+
+    fn advance(&mut self) -> Token<'sc> {
+        let token = self.tokens[self.pos].clone();
+        if token.kind != TokenKind::Eof {
+            self.pos += 1;
+        }
+        token
+    }
+
+    fn eat(&mut self, kind: &TokenKind<'sc>) -> bool {
+        if self.peek() == kind {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    // byte offset of the next token, to be handed to `span_from` once the
+    // node starting there has been parsed
+    fn start(&self) -> usize {
+        self.tokens[self.pos].start
+    }
+
+    // the span from `start` up to the end of the last token consumed
+    fn span_from(&self, start: usize) -> Span<'sc> {
+        let end = match self.pos {
+            0 => start,
+            pos => self.tokens[pos - 1].end.max(start),
+        };
+        Span::new(self.src, start, end)
+    }
+
+    fn error<T>(&self, message: impl Into<String>) -> Result<T, ParseError> {
+        Err(ParseError::new(message, self.start()))
+    }
+
+    fn unexpected<T>(&self, expected: &str) -> Result<T, ParseError> {
+        self.error(format!("Expected {}, found {}", expected, self.peek()))
+    }
+
+    fn expect(&mut self, kind: &TokenKind<'sc>) -> Result<Token<'sc>, ParseError> {
+        if self.peek() == kind {
+            Ok(self.advance())
+        } else {
+            self.unexpected(&kind.to_string())
+        }
+    }
+
+    fn expect_ident(&mut self) -> Result<Ident<'sc>, ParseError> {
+        match *self.peek() {
+            TokenKind::Ident(name) if name != "_" => {
+                let token = self.advance();
+                let span = Span::new(self.src, token.start, token.end);
+                Ok(Ident::new(name, span))
+            }
+            _ => self.unexpected("an identifier"),
+        }
+    }
+
+    // parses statements up to, but not including, `terminator`
+    fn parse_nodes(&mut self, terminator: &TokenKind<'sc>) -> Result<Vec<Node<'sc>>, ParseError> {
+        let mut nodes = vec![];
+        loop {
+            while self.eat(&TokenKind::Semicolon) {}
+            if self.peek() == terminator {
+                return Ok(nodes);
+            }
+            let (node, needs_semicolon) = self.parse_node()?;
+            nodes.push(node);
+            if self.eat(&TokenKind::Semicolon) || self.peek() == terminator {
+                continue;
+            }
+            if needs_semicolon {
+                return self.unexpected("`;`");
+            }
+        }
+    }
+
+    // returns the node along with whether it must be followed by a `;` when it
+    // isn't the last node in its block
+    fn parse_node(&mut self) -> Result<(Node<'sc>, bool), ParseError> {
+        let start = self.start();
+        match self.peek() {
+            TokenKind::Let => {
+                self.advance();
+                let is_mutable = self.eat(&TokenKind::Mut);
+                // `_` is a pattern, not a name
+                let is_pattern = !matches!(
+                    (self.peek(), self.peek_second()),
+                    (TokenKind::Ident(name), TokenKind::Assign) if *name != "_"
+                );
+                if is_pattern {
+                    let pattern = self.parse_scrutinee()?;
+                    self.expect(&TokenKind::Assign)?;
+                    let body = self.parse_expression(true)?;
+                    let node = Node::Declaration(Declaration::DestructuringDeclaration(
+                        DestructuringDeclaration {
+                            pattern,
+                            body,
+                            is_mutable,
+                            span: self.span_from(start),
+                        },
+                    ));
+                    return Ok((node, true));
+                }
+                let name = self.expect_ident()?;
+                self.expect(&TokenKind::Assign)?;
+                let body = self.parse_expression(true)?;
+                let node =
+                    Node::Declaration(Declaration::VariableDeclaration(VariableDeclaration {
+                        name,
+                        body,
+                        is_mutable,
+                        span: self.span_from(start),
+                    }));
+                Ok((node, true))
+            }
+            TokenKind::While => {
+                self.advance();
+                let condition = self.parse_expression(false)?;
+                let body = self.parse_code_block()?;
+                let node = Node::WhileLoop(WhileLoop {
+                    condition,
+                    body,
+                    span: self.span_from(start),
+                });
+                Ok((node, false))
+            }
+            TokenKind::Return => {
+                self.advance();
+                let expr = self.parse_expression(true)?;
+                let node = Node::ReturnStatement(ReturnStatement {
+                    expr,
+                    span: self.span_from(start),
+                });
+                Ok((node, true))
+            }
+            TokenKind::If => {
+                let node = Node::IfExpression(self.parse_if()?);
+                Ok((node, false))
+            }
+            TokenKind::Match => {
+                let node = Node::MatchStatement(self.parse_match()?);
+                Ok((node, false))
+            }
+            TokenKind::Enum => {
+                let node = Node::Declaration(Declaration::EnumDeclaration(self.parse_enum()?));
+                Ok((node, false))
+            }
+            TokenKind::Struct => {
+                let node = Node::Declaration(Declaration::StructDeclaration(self.parse_struct()?));
+                Ok((node, false))
+            }
+            _ => {
+                let exp = self.parse_expression(true)?;
+                if self.eat(&TokenKind::Assign) {
+                    let rhs = self.parse_expression(true)?;
+                    let node = Node::Declaration(Declaration::Reassignment(Reassignment {
+                        lhs: Box::new(exp),
+                        rhs,
+                        span: self.span_from(start),
+                    }));
+                    return Ok((node, true));
+                }
+                let needs_semicolon = !matches!(
+                    exp,
+                    Expression::CodeBlock { .. }
+                        | Expression::IfExp { .. }
+                        | Expression::Match { .. }
+                );
+                Ok((Node::Expression(exp), needs_semicolon))
+            }
+        }
+    }
+
+    // `enum Name { A, B(u32, bool) }`
+    fn parse_enum(&mut self) -> Result<EnumDeclaration<'sc>, ParseError> {
+        let start = self.start();
+        self.expect(&TokenKind::Enum)?;
+        let name = self.expect_ident()?;
+        self.expect(&TokenKind::OpenBrace)?;
+        let mut variants: Vec<EnumVariant> = vec![];
+        while !self.eat(&TokenKind::CloseBrace) {
+            let variant_start = self.start();
+            let variant_name = self.expect_ident()?;
+            if variants
+                .iter()
+                .any(|variant| variant.name.primary_name == variant_name.primary_name)
+            {
+                return Err(ParseError::new(
+                    format!("Variant {} is declared twice", variant_name.name()),
+                    variant_start,
+                ));
+            }
+            let mut payload = vec![];
+            if self.eat(&TokenKind::OpenParen) {
+                while !self.eat(&TokenKind::CloseParen) {
+                    payload.push(self.expect_ident()?);
+                    if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseParen {
+                        return self.unexpected("`,` or `)`");
+                    }
+                }
+            }
+            variants.push(EnumVariant {
+                name: variant_name,
+                payload,
+            });
+            if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseBrace {
+                return self.unexpected("`,` or `}`");
+            }
+        }
+        Ok(EnumDeclaration {
+            name,
+            variants,
+            span: self.span_from(start),
+        })
+    }
+
+    // `struct Name { x: u32, y: bool }`
+    fn parse_struct(&mut self) -> Result<StructDeclaration<'sc>, ParseError> {
+        let start = self.start();
+        self.expect(&TokenKind::Struct)?;
+        let name = self.expect_ident()?;
+        self.expect(&TokenKind::OpenBrace)?;
+        let mut fields: Vec<StructField> = vec![];
+        while !self.eat(&TokenKind::CloseBrace) {
+            let field_start = self.start();
+            let field_name = self.expect_ident()?;
+            if fields
+                .iter()
+                .any(|field| field.name.primary_name == field_name.primary_name)
+            {
+                return Err(ParseError::new(
+                    format!("Field {} is declared twice", field_name.name()),
+                    field_start,
+                ));
+            }
+            self.expect(&TokenKind::Colon)?;
+            let r#type = self.expect_ident()?;
+            fields.push(StructField {
+                name: field_name,
+                r#type,
+            });
+            if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseBrace {
+                return self.unexpected("`,` or `}`");
+            }
+        }
+        Ok(StructDeclaration {
+            name,
+            fields,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_code_block(&mut self) -> Result<CodeBlock<'sc>, ParseError> {
+        self.expect(&TokenKind::OpenBrace)?;
+        let contents = self.parse_nodes(&TokenKind::CloseBrace)?;
+        self.expect(&TokenKind::CloseBrace)?;
+        Ok(CodeBlock { contents })
+    }
+
+    fn parse_block(&mut self) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        let contents = self.parse_code_block()?;
+        Ok(Expression::CodeBlock {
+            contents,
+            span: self.span_from(start),
+        })
+    }
+
+    // `if` is shared between statements and expressions, the statement form
+    // is used as is and the expression form is built from its parts
+    fn parse_if(&mut self) -> Result<IfExpression<'sc>, ParseError> {
+        let start = self.start();
+        self.expect(&TokenKind::If)?;
+        let primary = self.parse_expression(false)?;
+        let left = self.parse_block()?;
+        let right = if self.eat(&TokenKind::Else) {
+            match self.peek() {
+                TokenKind::If => Some(self.parse_if_expression()?),
+                _ => Some(self.parse_block()?),
+            }
+        } else {
+            None
+        };
+        Ok(IfExpression {
+            primary,
+            left,
+            right,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_if_expression(&mut self) -> Result<Expression<'sc>, ParseError> {
+        let IfExpression {
+            primary,
+            left,
+            right,
+            span,
+        } = self.parse_if()?;
+        Ok(Expression::IfExp {
+            condition: Box::new(primary),
+            then: Box::new(left),
+            r#else: right.map(Box::new),
+            span,
+        })
+    }
+
+    // like `if`, `match` is parsed the same way as a statement and as an
+    // expression
+    fn parse_match(&mut self) -> Result<MatchStatement<'sc>, ParseError> {
+        let start = self.start();
+        self.expect(&TokenKind::Match)?;
+        let primary = self.parse_expression(false)?;
+        let branches = self.parse_match_branches()?;
+        Ok(MatchStatement {
+            primary,
+            branches,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_match_expression(&mut self) -> Result<Expression<'sc>, ParseError> {
+        let MatchStatement {
+            primary,
+            branches,
+            span,
+        } = self.parse_match()?;
+        Ok(Expression::Match {
+            primary: Box::new(primary),
+            branches,
+            span,
+        })
+    }
+
+    fn parse_match_branches(&mut self) -> Result<Vec<MatchBranch<'sc>>, ParseError> {
+        self.expect(&TokenKind::OpenBrace)?;
+        let mut branches = vec![];
+        while !self.eat(&TokenKind::CloseBrace) {
+            let start = self.start();
+            let condition = match self.peek() {
+                TokenKind::Ident("_")
+                    if matches!(self.peek_second(), TokenKind::FatArrow | TokenKind::If) =>
+                {
+                    self.advance();
+                    MatchScrutinee::CatchAll
+                }
+                _ => MatchScrutinee::Scrutinee(self.parse_scrutinee()?),
+            };
+            let guard = if self.eat(&TokenKind::If) {
+                Some(self.parse_expression(true)?)
+            } else {
+                None
+            };
+            self.expect(&TokenKind::FatArrow)?;
+            let result = self.parse_expression(true)?;
+            let is_block = matches!(result, Expression::CodeBlock { .. });
+            branches.push(MatchBranch {
+                condition,
+                guard,
+                result,
+                span: self.span_from(start),
+            });
+            if !self.eat(&TokenKind::Comma) && !is_block && self.peek() != &TokenKind::CloseBrace {
+                return self.unexpected("`,`");
+            }
+        }
+        Ok(branches)
+    }
+
+    fn parse_scrutinee(&mut self) -> Result<Scrutinee<'sc>, ParseError> {
+        let start = self.start();
+        let first = self.parse_single_scrutinee()?;
+        if self.peek() != &TokenKind::Pipe {
+            return Ok(first);
+        }
+        let mut alternatives = vec![first];
+        while self.eat(&TokenKind::Pipe) {
+            alternatives.push(self.parse_single_scrutinee()?);
+        }
+        Ok(Scrutinee::Or {
+            alternatives,
+            span: self.span_from(start),
+        })
+    }
+
+    fn parse_single_scrutinee(&mut self) -> Result<Scrutinee<'sc>, ParseError> {
+        let start = self.start();
+        match *self.peek() {
+            TokenKind::Literal(ref value)
+                if matches!(self.peek_second(), TokenKind::DotDot | TokenKind::DotDotEq) =>
+            {
+                let value = value.clone();
+                self.advance();
+                self.parse_range(start, Some(value))
+            }
+            TokenKind::DotDotEq => self.parse_range(start, None),
+            TokenKind::Literal(ref value) => {
+                let value = value.clone();
+                self.advance();
+                Ok(Scrutinee::Literal {
+                    value,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident("_") => {
+                self.advance();
+                Ok(Scrutinee::Wildcard {
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) if self.peek_second() == &TokenKind::OpenBrace => {
+                let struct_name = self.expect_ident()?;
+                self.expect(&TokenKind::OpenBrace)?;
+                let mut fields = vec![];
+                let mut rest = false;
+                while !self.eat(&TokenKind::CloseBrace) {
+                    if self.eat(&TokenKind::DotDot) {
+                        rest = true;
+                        self.expect(&TokenKind::CloseBrace)?;
+                        break;
+                    }
+                    let field_name = self.expect_ident()?;
+                    let scrutinee = if self.eat(&TokenKind::Colon) {
+                        self.parse_scrutinee()?
+                    } else {
+                        Scrutinee::VariableExpression {
+                            name: field_name.clone(),
+                            span: field_name.span,
+                        }
+                    };
+                    fields.push(StructScrutineeField {
+                        field_name,
+                        scrutinee,
+                    });
+                    if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseBrace {
+                        return self.unexpected("`,` or `}`");
+                    }
+                }
+                Ok(Scrutinee::StructScrutinee {
+                    struct_name,
+                    fields,
+                    rest,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) if self.peek_second() == &TokenKind::ColonColon => {
+                let enum_name = self.expect_ident()?;
+                self.advance();
+                let variant_name = self.expect_ident()?;
+                let payload = if self.eat(&TokenKind::OpenParen) {
+                    self.parse_scrutinee_elems(&TokenKind::CloseParen)?.0
+                } else {
+                    vec![]
+                };
+                if payload
+                    .iter()
+                    .any(|elem| matches!(elem, Scrutinee::Rest { .. }))
+                {
+                    return Err(ParseError::new(
+                        "`..` is not supported in enum patterns",
+                        start,
+                    ));
+                }
+                Ok(Scrutinee::EnumVariant {
+                    enum_name,
+                    variant_name,
+                    payload,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) if self.peek_second() == &TokenKind::At => {
+                let name = self.expect_ident()?;
+                self.advance();
+                let sub = self.parse_single_scrutinee()?;
+                Ok(Scrutinee::Binding {
+                    name,
+                    sub: Box::new(sub),
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) => {
+                let name = self.expect_ident()?;
+                let span = name.span;
+                Ok(Scrutinee::VariableExpression { name, span })
+            }
+            TokenKind::OpenParen => {
+                self.advance();
+                let (mut elems, is_tuple) = self.parse_scrutinee_elems(&TokenKind::CloseParen)?;
+                match elems.len() {
+                    0 => self.error("Unit patterns are not supported"),
+                    1 if !is_tuple => Ok(elems.pop().unwrap()),
+                    _ => Ok(Scrutinee::Tuple {
+                        elems,
+                        span: self.span_from(start),
+                    }),
+                }
+            }
+            TokenKind::OpenBracket => {
+                self.advance();
+                let (elems, _) = self.parse_scrutinee_elems(&TokenKind::CloseBracket)?;
+                Ok(Scrutinee::Array {
+                    elems,
+                    span: self.span_from(start),
+                })
+            }
+            _ => self.unexpected("a pattern"),
+        }
+    }
+
+    // The elements of a tuple or array pattern up to and including `close`,
+    // and whether they can only be a tuple, as opposed to a single pattern
+    // in parentheses.
+    fn parse_scrutinee_elems(
+        &mut self,
+        close: &TokenKind<'sc>,
+    ) -> Result<(Vec<Scrutinee<'sc>>, bool), ParseError> {
+        let mut elems = vec![];
+        let mut is_tuple = false;
+        while !self.eat(close) {
+            if self.peek() == &TokenKind::DotDot {
+                if elems
+                    .iter()
+                    .any(|elem| matches!(elem, Scrutinee::Rest { .. }))
+                {
+                    return self.error("`..` can only be used once per pattern");
+                }
+                let elem_start = self.start();
+                self.advance();
+                // `(..)` is a tuple pattern, not a parenthesized `..`
+                is_tuple = true;
+                elems.push(Scrutinee::Rest {
+                    span: self.span_from(elem_start),
+                });
+            } else {
+                elems.push(self.parse_scrutinee()?);
+            }
+            if self.eat(&TokenKind::Comma) {
+                is_tuple = true;
+            } else if self.peek() != close {
+                return self.unexpected(&format!("`,` or {}", close));
+            }
+        }
+        Ok((elems, is_tuple))
+    }
+
+    // `start..`, `start..end`, `start..=end` and `..=end`, with the parser
+    // just past `start`
+    fn parse_range(
+        &mut self,
+        start: usize,
+        range_start: Option<Literal<'sc>>,
+    ) -> Result<Scrutinee<'sc>, ParseError> {
+        let inclusive = self.peek() == &TokenKind::DotDotEq;
+        self.advance();
+        let range_end = match *self.peek() {
+            TokenKind::Literal(ref value) => {
+                let value = value.clone();
+                self.advance();
+                Some(value)
+            }
+            _ if inclusive => return self.unexpected("the end of the range"),
+            _ => None,
+        };
+        let bounds = range_start.iter().chain(range_end.iter());
+        if bounds.clone().any(|bound| bound.integer_value().is_none()) {
+            return self.error("Range patterns only work on integers");
+        }
+        if let (Some(low), Some(high)) = (&range_start, &range_end) {
+            if mem::discriminant(low) != mem::discriminant(high) {
+                return self.error("The ends of a range pattern have different types");
+            }
+            let (low, high) = (low.integer_value(), high.integer_value());
+            if low > high || (low == high && !inclusive) {
+                return self.error("Range pattern is empty");
+            }
+        }
+        Ok(Scrutinee::Range {
+            start: range_start,
+            end: range_end,
+            inclusive,
+            span: self.span_from(start),
+        })
+    }
+
+    // struct literals are not allowed directly in the head of `if`, `while`
+    // and `match`, where the `{` would be ambiguous with the body
+    fn parse_expression(&mut self, allow_struct: bool) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        let mut left = self.parse_conjunction(allow_struct)?;
+        while self.eat(&TokenKind::OrOr) {
+            let right = self.parse_conjunction(allow_struct)?;
+            left = Expression::BinOp {
+                op2: Op2::Or,
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(start),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_conjunction(&mut self, allow_struct: bool) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        let mut left = self.parse_equality(allow_struct)?;
+        while self.eat(&TokenKind::AndAnd) {
+            let right = self.parse_equality(allow_struct)?;
+            left = Expression::BinOp {
+                op2: Op2::And,
+                left: Box::new(left),
+                right: Box::new(right),
+                span: self.span_from(start),
+            };
+        }
+        Ok(left)
+    }
+
+    fn parse_equality(&mut self, allow_struct: bool) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        let left = self.parse_projection(allow_struct)?;
+        let op2 = match self.peek() {
+            TokenKind::EqEq => Op2::Eq,
+            TokenKind::Lt => Op2::Lt,
+            TokenKind::Le => Op2::Le,
+            TokenKind::Gt => Op2::Gt,
+            TokenKind::Ge => Op2::Ge,
+            _ => return Ok(left),
+        };
+        self.advance();
+        let right = self.parse_projection(allow_struct)?;
+        Ok(Expression::BinOp {
+            op2,
+            left: Box::new(left),
+            right: Box::new(right),
+            span: self.span_from(start),
+        })
+    }
+
+    // `exp.0`, `exp.field` and `exp[0]`
+    fn parse_projection(&mut self, allow_struct: bool) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        let mut exp = self.parse_primary(allow_struct)?;
+        loop {
+            if self.eat(&TokenKind::OpenBracket) {
+                let index = match *self.peek() {
+                    TokenKind::Literal(Literal::U32(index)) => index as usize,
+                    _ => return self.unexpected("an array index"),
+                };
+                self.advance();
+                self.expect(&TokenKind::CloseBracket)?;
+                exp = Expression::ArrayIndex {
+                    prefix: Box::new(exp),
+                    index,
+                    span: self.span_from(start),
+                };
+                continue;
+            }
+            if !self.eat(&TokenKind::Dot) {
+                break;
+            }
+            exp = match *self.peek() {
+                TokenKind::Literal(Literal::U32(index)) => {
+                    self.advance();
+                    Expression::TupleIndex {
+                        prefix: Box::new(exp),
+                        index: index as usize,
+                        span: self.span_from(start),
+                    }
+                }
+                TokenKind::Ident(_) => {
+                    let field_name = self.expect_ident()?;
+                    Expression::SubfieldExpression {
+                        prefix: Box::new(exp),
+                        field_name,
+                        span: self.span_from(start),
+                    }
+                }
+                _ => return self.unexpected("a field name or tuple index"),
+            };
+        }
+        Ok(exp)
+    }
+
+    fn parse_primary(&mut self, allow_struct: bool) -> Result<Expression<'sc>, ParseError> {
+        let start = self.start();
+        match *self.peek() {
+            TokenKind::Literal(ref value) => {
+                let value = value.clone();
+                self.advance();
+                Ok(Expression::Literal {
+                    value,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) if allow_struct && self.peek_second() == &TokenKind::OpenBrace => {
+                let struct_name = self.expect_ident()?;
+                self.expect(&TokenKind::OpenBrace)?;
+                let mut fields = vec![];
+                while !self.eat(&TokenKind::CloseBrace) {
+                    let name = self.expect_ident()?;
+                    let value = if self.eat(&TokenKind::Colon) {
+                        self.parse_expression(true)?
+                    } else {
+                        Expression::VariableExpression {
+                            name: name.clone(),
+                            span: name.span,
+                        }
+                    };
+                    fields.push(StructExpressionField { name, value });
+                    if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseBrace {
+                        return self.unexpected("`,` or `}`");
+                    }
+                }
+                Ok(Expression::StructExpression {
+                    struct_name,
+                    fields,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) if self.peek_second() == &TokenKind::ColonColon => {
+                let enum_name = self.expect_ident()?;
+                self.advance();
+                let variant_name = self.expect_ident()?;
+                let mut payload = vec![];
+                if self.eat(&TokenKind::OpenParen) {
+                    while !self.eat(&TokenKind::CloseParen) {
+                        payload.push(self.parse_expression(true)?);
+                        if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseParen {
+                            return self.unexpected("`,` or `)`");
+                        }
+                    }
+                }
+                Ok(Expression::EnumInstantiation {
+                    enum_name,
+                    variant_name,
+                    payload,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::Ident(_) => {
+                let name = self.expect_ident()?;
+                let span = name.span;
+                Ok(Expression::VariableExpression { name, span })
+            }
+            TokenKind::OpenParen => {
+                self.advance();
+                let mut elems = vec![];
+                let mut is_tuple = false;
+                while !self.eat(&TokenKind::CloseParen) {
+                    elems.push(self.parse_expression(true)?);
+                    if self.eat(&TokenKind::Comma) {
+                        is_tuple = true;
+                    } else if self.peek() != &TokenKind::CloseParen {
+                        return self.unexpected("`,` or `)`");
+                    }
+                }
+                let span = self.span_from(start);
+                match elems.len() {
+                    0 => Ok(Expression::Unit { span }),
+                    1 if !is_tuple => Ok(elems.pop().unwrap()),
+                    _ => Ok(Expression::Tuple { elems, span }),
+                }
+            }
+            TokenKind::OpenBracket => {
+                self.advance();
+                let mut contents = vec![];
+                while !self.eat(&TokenKind::CloseBracket) {
+                    contents.push(self.parse_expression(true)?);
+                    if !self.eat(&TokenKind::Comma) && self.peek() != &TokenKind::CloseBracket {
+                        return self.unexpected("`,` or `]`");
+                    }
+                }
+                Ok(Expression::Array {
+                    contents,
+                    span: self.span_from(start),
+                })
+            }
+            TokenKind::OpenBrace => self.parse_block(),
+            TokenKind::If => self.parse_if_expression(),
+            TokenKind::Match => self.parse_match_expression(),
+            _ => self.unexpected("an expression"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        interpreter::interpret,
+        language::{constructors::*, MatchScrutinee, Node, Op2},
+        parser::parse,
+        value::Value,
+    };
+
+    #[test]
+    fn parse_declarations() {
+        let tree = parse("let x = 5; let mut y = (x, true); y = (1, false); y").unwrap();
+        let oracle = vec![
+            variable_declaraction("x", literal(u32_(5)), false),
+            variable_declaraction(
+                "y",
+                tuple(vec![variable("x"), literal(boolean(true))]),
+                true,
+            ),
+            reassignment(
+                variable("y"),
+                tuple(vec![literal(u32_(1)), literal(boolean(false))]),
+            ),
+            expression(variable("y")),
+        ];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_match() {
+        let src = "
+            match foo {
+                Point { x, y: 7 } => x,
+                Point { x, y } => { y }
+                _ => 42,
+            }
+        ";
+        let tree = parse(src).unwrap();
+        let oracle = vec![match_(
+            variable("foo"),
+            vec![
+                match_branch(
+                    match_scrutinee(struct_scrutinee(
+                        "Point",
+                        vec![
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
+                        ],
+                    )),
+                    variable("x"),
+                ),
+                match_branch(
+                    match_scrutinee(struct_scrutinee(
+                        "Point",
+                        vec![
+                            struct_scrutinee_field("x", variable_scrutinee("x")),
+                            struct_scrutinee_field("y", variable_scrutinee("y")),
+                        ],
+                    )),
+                    block(vec![expression(variable("y"))]),
+                ),
+                match_branch(match_scrutinee_catchall(), literal(u32_(42))),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_projections() {
+        let tree = parse("pair.0.x == 5").unwrap();
+        let oracle = vec![expression(binop_eq(
+            subfield(tuple_index(variable("pair"), 0), "x"),
+            literal(u32_(5)),
+        ))];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_if_else_chain() {
+        let tree = parse("if a { 1 } else if b { 2 } else { 3 }").unwrap();
+        let oracle = vec![if_statement(
+            variable("a"),
+            block(vec![expression(literal(u32_(1)))]),
+            Some(if_expression(
+                variable("b"),
+                block(vec![expression(literal(u32_(2)))]),
+                Some(block(vec![expression(literal(u32_(3)))])),
+            )),
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_spans() {
+        let src = "match foo {\n    (x, 5) => x,\n    _ => 0,\n}";
+        let tree = parse(src).unwrap();
+        let match_statement = match &tree.nodes[0] {
+            Node::MatchStatement(match_statement) => match_statement,
+            node => panic!("expected a match statement, found {}", node),
+        };
+        assert_eq!(match_statement.span.as_str(), src);
+        assert_eq!(match_statement.primary.span().as_str(), "foo");
+        let branch = &match_statement.branches[0];
+        assert_eq!(branch.span.as_str(), "(x, 5) => x");
+        assert_eq!(branch.span.line_col(), (2, 5));
+        match &branch.condition {
+            MatchScrutinee::Scrutinee(scrutinee) => assert_eq!(scrutinee.span().as_str(), "(x, 5)"),
+            MatchScrutinee::CatchAll => panic!("expected a scrutinee"),
+        }
+    }
+
+    #[test]
+    fn parse_missing_semicolon() {
+        let err = parse("let x = 5 let y = 6").unwrap_err();
+        assert_eq!(err.position, 10);
+    }
+
+    #[test]
+    fn parse_and_interpret() {
+        let src = "
+            struct Point { x: u32, y: u32 }
+            let mut done = false;
+            let mut total = 0u8;
+            while done == false {
+                total = 9u8;
+                done = true;
+            }
+            let pair = (total, Point { x: 1, y: 2 });
+            match pair {
+                (9u8, Point { x, y: 2 }) => { return x; }
+                _ => 0,
+            }
+            100
+        ";
+        let tree = parse(src).unwrap();
+        assert_eq!(interpret(tree), Ok(Value::U32(1)));
+    }
+
+    #[test]
+    fn parse_rest_patterns() {
+        let src = "
+            struct Point { x: u32, y: u32 }
+            let triple = (1, Point { x: 2, y: 3 }, 4);
+            match triple {
+                (.., 5) => 0,
+                (first, Point { y, .. }, ..) => (first, y),
+            }
+        ";
+        let tree = parse(src).unwrap();
+        assert_eq!(
+            interpret(tree),
+            Ok(Value::Tuple(vec![Value::U32(1), Value::U32(3)]))
+        );
+        assert!(parse("match t { (.., x, ..) => x }").is_err());
+        assert!(parse("match t { Point { .., x } => x }").is_err());
+    }
+
+    #[test]
+    fn parse_nested_wildcard() {
+        let tree = parse("match pair { (_, 1) => 1, _ => 0 }").unwrap();
+        let oracle = vec![match_(
+            variable("pair"),
+            vec![
+                match_branch(
+                    match_scrutinee(tuple_scrutinee(vec![
+                        wildcard_scrutinee(),
+                        literal_scrutinee(u32_(1)),
+                    ])),
+                    literal(u32_(1)),
+                ),
+                match_branch(match_scrutinee_catchall(), literal(u32_(0))),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_or_patterns() {
+        let tree = parse("match pair { (0, _) | (_, 0) => a || b, _ => c }").unwrap();
+        let oracle = vec![match_(
+            variable("pair"),
+            vec![
+                match_branch(
+                    match_scrutinee(or_scrutinee(vec![
+                        tuple_scrutinee(vec![literal_scrutinee(u32_(0)), wildcard_scrutinee()]),
+                        tuple_scrutinee(vec![wildcard_scrutinee(), literal_scrutinee(u32_(0))]),
+                    ])),
+                    binop_or(variable("a"), variable("b")),
+                ),
+                match_branch(match_scrutinee_catchall(), variable("c")),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_match_guards() {
+        let tree = parse("match n { x if x == limit => 1, _ if ok => 2, _ => 0 }").unwrap();
+        let oracle = vec![match_(
+            variable("n"),
+            vec![
+                guarded_match_branch(
+                    match_scrutinee(variable_scrutinee("x")),
+                    binop_eq(variable("x"), variable("limit")),
+                    literal(u32_(1)),
+                ),
+                guarded_match_branch(match_scrutinee_catchall(), variable("ok"), literal(u32_(2))),
+                match_branch(match_scrutinee_catchall(), literal(u32_(0))),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_binding_patterns() {
+        let tree = parse("match p { whole @ Point { x, .. } => x }").unwrap();
+        let oracle = vec![match_(
+            variable("p"),
+            vec![match_branch(
+                match_scrutinee(binding_scrutinee(
+                    "whole",
+                    struct_scrutinee_with_rest(
+                        "Point",
+                        vec![struct_scrutinee_field("x", variable_scrutinee("x"))],
+                    ),
+                )),
+                variable("x"),
+            )],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_range_patterns() {
+        let tree =
+            parse("match n { 0..=9 => n < 5, 10..20 => n >= 15, ..=30 => true, 31.. => false }")
+                .unwrap();
+        let range = |start: Option<u32>, end: Option<u32>, inclusive| {
+            range_scrutinee(start.map(u32_), end.map(u32_), inclusive)
+        };
+        let oracle = vec![match_(
+            variable("n"),
+            vec![
+                match_branch(
+                    match_scrutinee(range(Some(0), Some(9), true)),
+                    binop(Op2::Lt, variable("n"), literal(u32_(5))),
+                ),
+                match_branch(
+                    match_scrutinee(range(Some(10), Some(20), false)),
+                    binop(Op2::Ge, variable("n"), literal(u32_(15))),
+                ),
+                match_branch(
+                    match_scrutinee(range(None, Some(30), true)),
+                    literal(boolean(true)),
+                ),
+                match_branch(
+                    match_scrutinee(range(Some(31), None, false)),
+                    literal(boolean(false)),
+                ),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+
+        assert!(parse("match n { 5..=1 => 0 }").is_err());
+        assert!(parse("match n { 1u8..=5u32 => 0 }").is_err());
+    }
+
+    #[test]
+    fn parse_array_patterns() {
+        let tree = parse("match xs { [] => 0, [x] => x, [first, .., 0] => first, [..] => xs[1] }")
+            .unwrap();
+        let oracle = vec![match_(
+            variable("xs"),
+            vec![
+                match_branch(match_scrutinee(array_scrutinee(vec![])), literal(u32_(0))),
+                match_branch(
+                    match_scrutinee(array_scrutinee(vec![variable_scrutinee("x")])),
+                    variable("x"),
+                ),
+                match_branch(
+                    match_scrutinee(array_scrutinee(vec![
+                        variable_scrutinee("first"),
+                        rest_scrutinee(),
+                        literal_scrutinee(u32_(0)),
+                    ])),
+                    variable("first"),
+                ),
+                match_branch(
+                    match_scrutinee(array_scrutinee(vec![rest_scrutinee()])),
+                    array_index(variable("xs"), 1),
+                ),
+            ],
+        )];
+        assert_eq!(tree.nodes, oracle);
+    }
+
+    #[test]
+    fn parse_enums() {
+        let src = "
+            enum Shape { Dot, Circle(u32), Rect(u32, u32), }
+            match Shape::Rect(2, 3) {
+                Shape::Dot => 0,
+                Shape::Circle(r) => r,
+                Shape::Rect(w, _) => w,
+            }
+        ";
+        let tree = parse(src).unwrap();
+        let oracle = vec![
+            enum_declaration(
+                "Shape",
+                vec![
+                    ("Dot", vec![]),
+                    ("Circle", vec!["u32"]),
+                    ("Rect", vec!["u32", "u32"]),
+                ],
+            ),
+            match_(
+                enum_instantiation("Shape", "Rect", vec![literal(u32_(2)), literal(u32_(3))]),
+                vec![
+                    match_branch(
+                        match_scrutinee(enum_scrutinee("Shape", "Dot", vec![])),
+                        literal(u32_(0)),
+                    ),
+                    match_branch(
+                        match_scrutinee(enum_scrutinee(
+                            "Shape",
+                            "Circle",
+                            vec![variable_scrutinee("r")],
+                        )),
+                        variable("r"),
+                    ),
+                    match_branch(
+                        match_scrutinee(enum_scrutinee(
+                            "Shape",
+                            "Rect",
+                            vec![variable_scrutinee("w"), wildcard_scrutinee()],
+                        )),
+                        variable("w"),
+                    ),
+                ],
+            ),
+        ];
+        assert_eq!(tree.nodes, oracle);
+        assert!(parse("enum E { A, A }").is_err());
+        assert!(parse("match e { E::A(..) => 0 }").is_err());
+    }
+
+    #[test]
+    fn parse_struct_declarations() {
+        let tree = parse("struct Point { x: u32, y: bool, }").unwrap();
+        let oracle = vec![struct_declaration(
+            "Point",
+            vec![("x", "u32"), ("y", "bool")],
+        )];
+        assert_eq!(tree.nodes, oracle);
+        assert_eq!(
+            tree.nodes[0].to_string(),
+            "struct Point { x: u32, y: bool }"
+        );
+        assert!(parse("struct Point { x: u32, x: u32 }").is_err());
+        assert!(parse("struct Point { x }").is_err());
+    }
+
+    #[test]
+    fn parse_match_expressions() {
+        let src = "
+            let x = 2;
+            let pair = (match x { 1 => 10, n => n }, if true { match x { _ => 3 } } else { 0 });
+            pair
+        ";
+        let tree = parse(src).unwrap();
+        assert_eq!(
+            tree.nodes[1],
+            variable_declaraction(
+                "pair",
+                tuple(vec![
+                    match_expression(
+                        variable("x"),
+                        vec![
+                            match_branch(
+                                match_scrutinee(literal_scrutinee(u32_(1))),
+                                literal(u32_(10)),
+                            ),
+                            match_branch(match_scrutinee(variable_scrutinee("n")), variable("n")),
+                        ],
+                    ),
+                    if_expression(
+                        literal(boolean(true)),
+                        // at the start of a block, it's a statement
+                        block(vec![match_(
+                            variable("x"),
+                            vec![match_branch(match_scrutinee_catchall(), literal(u32_(3)))],
+                        )]),
+                        Some(block(vec![expression(literal(u32_(0)))])),
+                    ),
+                ]),
+                false,
+            )
+        );
+        assert_eq!(
+            interpret(tree),
+            Ok(Value::Tuple(vec![Value::U32(2), Value::U32(3)]))
+        );
+    }
+
+    #[test]
+    fn parse_destructuring_declarations() {
+        let tree = parse("let (a, _) = pair; let mut Point { x, .. } = p; let _ = 1;").unwrap();
+        let oracle = vec![
+            destructuring_declaration(
+                tuple_scrutinee(vec![variable_scrutinee("a"), wildcard_scrutinee()]),
+                variable("pair"),
+                false,
+            ),
+            destructuring_declaration(
+                struct_scrutinee_with_rest(
+                    "Point",
+                    vec![struct_scrutinee_field("x", variable_scrutinee("x"))],
+                ),
+                variable("p"),
+                true,
+            ),
+            destructuring_declaration(wildcard_scrutinee(), literal(u32_(1)), false),
+        ];
+        assert_eq!(tree.nodes, oracle);
+        assert_eq!(tree.nodes[2].to_string(), "let _ = 1");
+        assert!(parse("let (a, b);").is_err());
+    }
+}
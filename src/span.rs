@@ -0,0 +1,74 @@
+use std::fmt;
+
+/// A byte range into the source a node was parsed from.
+///
+/// Spans are metadata: two nodes that only differ in where they came from
+/// compare equal, so that trees built by the parser, by the `constructors`
+/// and by the desugarer can be compared structurally.
+#[derive(Clone, Copy)]
+pub struct Span<'sc> {
+    pub src: &'sc str,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl<'sc> Span<'sc> {
+    pub fn new(src: &'sc str, start: usize, end: usize) -> Self {
+        Span { src, start, end }
+    }
+
+    /// A span for nodes that don't originate from any source text.
+    pub fn dummy() -> Self {
+        Span {
+            src: "",
+            start: 0,
+            end: 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &'sc str {
+        &self.src[self.start..self.end]
+    }
+
+    /// The smallest span covering both `self` and `other`.
+    pub fn join(&self, other: &Span<'sc>) -> Span<'sc> {
+        Span {
+            src: self.src,
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+
+    /// The 1-based line and column of the start of the span.
+    pub fn line_col(&self) -> (usize, usize) {
+        let before = &self.src[..self.start];
+        let line = before.matches('\n').count() + 1;
+        let col = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+        (line, col)
+    }
+}
+
+impl<'sc> PartialEq for Span<'sc> {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl<'sc> fmt::Debug for Span<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::span::Span;
+
+    #[test]
+    fn span_line_col() {
+        let src = "let x = 5;\nlet y = x;";
+        let span = Span::new(src, 19, 20);
+        assert_eq!(span.as_str(), "x");
+        assert_eq!(span.line_col(), (2, 9));
+    }
+}
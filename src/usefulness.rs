@@ -0,0 +1,1307 @@
+//! Static analysis of match arms, following Maranget's "Warnings for pattern
+//! matching": a pattern vector is *useful* with respect to a matrix of
+//! earlier rows if some value matches it but none of the rows. A match is
+//! exhaustive exactly when a row of wildcards is not useful with respect to
+//! all of its arms.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fmt;
+
+use crate::error::DesugarWarning;
+use crate::language::*;
+
+/// A pattern with the bindings erased, which is all that matters for
+/// exhaustiveness. Missing cases are reported in this form as well.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Pattern<'sc> {
+    Wild,
+    Constructor(Constructor<'sc>, Vec<Pattern<'sc>>),
+    Or(Vec<Pattern<'sc>>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Constructor<'sc> {
+    Literal(Literal<'sc>),
+    Tuple(usize),
+    // a tuple pattern with a `..`, and how many elements come before and
+    // after it. It stands for `Tuple` of any arity that fits, and is widened
+    // to one before being compared with other patterns.
+    TupleRest(usize, usize),
+    // an array of exactly this many elements
+    Array(usize),
+    // an array pattern with a `..`, and how many elements come before and
+    // after it. It stands for every array at least that long.
+    ArrayRest(usize, usize),
+    // the integers from the first to the second literal, inclusive
+    Range(Literal<'sc>, Literal<'sc>),
+    // name of the struct and the names of the fields in the pattern, sorted
+    Struct(&'sc str, Vec<&'sc str>),
+    // a variant of an enum, along with every variant of that enum and its
+    // arity, in the order they were declared
+    Variant {
+        enum_name: &'sc str,
+        variant_name: &'sc str,
+        arity: usize,
+        variants: Vec<(&'sc str, usize)>,
+    },
+}
+
+impl<'sc> Constructor<'sc> {
+    fn arity(&self) -> usize {
+        match self {
+            Constructor::Literal(_) | Constructor::Range(..) => 0,
+            Constructor::Tuple(arity) => *arity,
+            Constructor::TupleRest(before, after) | Constructor::ArrayRest(before, after) => {
+                before + after
+            }
+            Constructor::Array(length) => *length,
+            Constructor::Struct(_, field_names) => field_names.len(),
+            Constructor::Variant { arity, .. } => *arity,
+        }
+    }
+}
+
+impl<'sc> Pattern<'sc> {
+    /// The pattern `scrutinee` stands for. Enum patterns take the rest of
+    /// their enum's variants from `namespace`.
+    pub fn new(scrutinee: &Scrutinee<'sc>, namespace: &Namespace<'sc>) -> Self {
+        match scrutinee {
+            Scrutinee::Literal { value, .. } => {
+                Pattern::Constructor(Constructor::Literal(value.clone()), vec![])
+            }
+            Scrutinee::Range {
+                start,
+                end,
+                inclusive,
+                ..
+            } => {
+                let width = match start.as_ref().or(end.as_ref()) {
+                    Some(width) => width,
+                    None => return Pattern::Wild,
+                };
+                let low = start.as_ref().and_then(Literal::integer_value).unwrap_or(0);
+                let high = match end.as_ref().and_then(Literal::integer_value) {
+                    Some(end) if *inclusive => Some(end),
+                    Some(end) => end.checked_sub(1),
+                    None => Some(integer_max(width)),
+                };
+                let constructor = match high {
+                    Some(high) => Constructor::Range(
+                        with_integer_value(width, low),
+                        with_integer_value(width, high),
+                    ),
+                    // `..0`, which matches nothing
+                    None => Constructor::Range(
+                        with_integer_value(width, 1),
+                        with_integer_value(width, 0),
+                    ),
+                };
+                Pattern::Constructor(constructor, vec![])
+            }
+            Scrutinee::Binding { sub, .. } => Pattern::new(sub, namespace),
+            Scrutinee::VariableExpression { .. }
+            | Scrutinee::Wildcard { .. }
+            | Scrutinee::Rest { .. } => Pattern::Wild,
+            Scrutinee::Tuple { elems, .. } => match Scrutinee::split_rest(elems) {
+                (elems, None) => Pattern::Constructor(
+                    Constructor::Tuple(elems.len()),
+                    elems
+                        .iter()
+                        .map(|elem| Pattern::new(elem, namespace))
+                        .collect(),
+                ),
+                (before, Some(after)) => Pattern::Constructor(
+                    Constructor::TupleRest(before.len(), after.len()),
+                    before
+                        .iter()
+                        .chain(after.iter())
+                        .map(|elem| Pattern::new(elem, namespace))
+                        .collect(),
+                ),
+            },
+            Scrutinee::Array { elems, .. } => match Scrutinee::split_rest(elems) {
+                (elems, None) => Pattern::Constructor(
+                    Constructor::Array(elems.len()),
+                    elems
+                        .iter()
+                        .map(|elem| Pattern::new(elem, namespace))
+                        .collect(),
+                ),
+                (before, Some(after)) => Pattern::Constructor(
+                    Constructor::ArrayRest(before.len(), after.len()),
+                    before
+                        .iter()
+                        .chain(after.iter())
+                        .map(|elem| Pattern::new(elem, namespace))
+                        .collect(),
+                ),
+            },
+            Scrutinee::StructScrutinee {
+                struct_name,
+                fields,
+                ..
+            } => {
+                let mut fields = fields
+                    .iter()
+                    .map(|field| {
+                        (
+                            field.field_name.primary_name,
+                            Pattern::new(&field.scrutinee, namespace),
+                        )
+                    })
+                    .collect::<Vec<_>>();
+                fields.sort_by_key(|(field_name, _)| *field_name);
+                let (field_names, fields) = fields.into_iter().unzip();
+                Pattern::Constructor(
+                    Constructor::Struct(struct_name.primary_name, field_names),
+                    fields,
+                )
+            }
+            Scrutinee::EnumVariant {
+                enum_name,
+                variant_name,
+                payload,
+                ..
+            } => {
+                let variants = namespace
+                    .get_enum(enum_name.primary_name)
+                    .map(|declaration| {
+                        declaration
+                            .variants
+                            .iter()
+                            .map(|variant| (variant.name.primary_name, variant.payload.len()))
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                Pattern::Constructor(
+                    Constructor::Variant {
+                        enum_name: enum_name.primary_name,
+                        variant_name: variant_name.primary_name,
+                        arity: payload.len(),
+                        variants,
+                    },
+                    payload
+                        .iter()
+                        .map(|elem| Pattern::new(elem, namespace))
+                        .collect(),
+                )
+            }
+            Scrutinee::Or { alternatives, .. } => Pattern::Or(
+                alternatives
+                    .iter()
+                    .map(|alternative| Pattern::new(alternative, namespace))
+                    .collect(),
+            ),
+        }
+    }
+
+    fn from_condition(condition: &MatchScrutinee<'sc>, namespace: &Namespace<'sc>) -> Self {
+        match condition {
+            MatchScrutinee::CatchAll => Pattern::Wild,
+            MatchScrutinee::Scrutinee(scrutinee) => Pattern::new(scrutinee, namespace),
+        }
+    }
+}
+
+impl<'sc> fmt::Display for Pattern<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Pattern::Wild => write!(f, "_"),
+            Pattern::Or(alternatives) => {
+                let alternatives = alternatives
+                    .iter()
+                    .map(Pattern::to_string)
+                    .collect::<Vec<_>>();
+                write!(f, "{}", alternatives.join(" | "))
+            }
+            Pattern::Constructor(constructor, fields) => {
+                let fields = fields.iter().map(Pattern::to_string).collect::<Vec<_>>();
+                match constructor {
+                    Constructor::Literal(value) => write!(f, "{}", value),
+                    Constructor::Range(low, high) => write!(f, "{}..={}", low, high),
+                    Constructor::Tuple(_) => write!(f, "({})", fields.join(", ")),
+                    Constructor::TupleRest(before, _) => {
+                        let mut fields = fields;
+                        fields.insert(*before, "..".to_string());
+                        write!(f, "({})", fields.join(", "))
+                    }
+                    Constructor::Array(_) => write!(f, "[{}]", fields.join(", ")),
+                    Constructor::ArrayRest(before, _) => {
+                        let mut fields = fields;
+                        fields.insert(*before, "..".to_string());
+                        write!(f, "[{}]", fields.join(", "))
+                    }
+                    Constructor::Struct(name, field_names) => {
+                        let fields = field_names
+                            .iter()
+                            .zip(fields.iter())
+                            .map(|(field_name, field)| format!("{}: {}", field_name, field))
+                            .collect::<Vec<_>>();
+                        write!(f, "{} {{ {} }}", name, fields.join(", "))
+                    }
+                    Constructor::Variant {
+                        enum_name,
+                        variant_name,
+                        ..
+                    } if fields.is_empty() => write!(f, "{}::{}", enum_name, variant_name),
+                    Constructor::Variant {
+                        enum_name,
+                        variant_name,
+                        ..
+                    } => write!(f, "{}::{}({})", enum_name, variant_name, fields.join(", ")),
+                }
+            }
+        }
+    }
+}
+
+/// Returns a value that none of `branches` match, if there is one. Arms with
+/// a guard might not match anything, so they don't count.
+pub fn check_exhaustiveness<'sc>(
+    branches: &[MatchBranch<'sc>],
+    namespace: &Namespace<'sc>,
+) -> Option<Pattern<'sc>> {
+    let matrix = branches
+        .iter()
+        .zip(arm_patterns(branches, namespace))
+        .filter(|(branch, _)| branch.guard.is_none())
+        .map(|(_, pattern)| vec![pattern])
+        .collect::<Vec<_>>();
+    is_useful(&matrix, &[Pattern::Wild]).map(|mut witness| witness.remove(0))
+}
+
+/// Flags every arm of `branches` that can never be selected because earlier
+/// arms match all of its values, and every refutable arm that shares some of
+/// its values with an earlier arm. Only arms without a guard can shadow
+/// others.
+pub fn check_redundancy<'sc>(
+    branches: &[MatchBranch<'sc>],
+    namespace: &Namespace<'sc>,
+) -> Vec<DesugarWarning<'sc>> {
+    let mut warnings = vec![];
+    let mut matrix: Vec<Vec<Pattern<'sc>>> = vec![];
+    let mut spans = vec![];
+    for (branch, pattern) in branches.iter().zip(arm_patterns(branches, namespace)) {
+        let row = vec![pattern];
+        if is_useful(&matrix, &row).is_none() {
+            warnings.push(DesugarWarning::UnreachableArm { span: branch.span });
+        } else if !is_irrefutable(&row[0]) {
+            // arms that match everything are expected to pick up whatever the
+            // earlier arms left over, so they aren't worth a warning
+            let shadowing = matrix
+                .iter()
+                .zip(spans.iter())
+                .find(|(earlier, _)| intersects(&earlier[0], &row[0]));
+            if let Some((_, shadowed_by)) = shadowing {
+                warnings.push(DesugarWarning::PartiallyShadowedArm {
+                    span: branch.span,
+                    shadowed_by: *shadowed_by,
+                });
+            }
+        }
+        if branch.guard.is_none() {
+            matrix.push(row);
+            spans.push(branch.span);
+        }
+    }
+    warnings
+}
+
+// A struct pattern only mentions the fields it cares about, so to line them up
+// every struct pattern is widened to all the fields that any arm mentions for
+// that struct, with wildcards for the ones it leaves out.
+fn arm_patterns<'sc>(
+    branches: &[MatchBranch<'sc>],
+    namespace: &Namespace<'sc>,
+) -> Vec<Pattern<'sc>> {
+    let patterns = branches
+        .iter()
+        .map(|branch| Pattern::from_condition(&branch.condition, namespace))
+        .collect::<Vec<_>>();
+    let mut struct_fields = HashMap::new();
+    for pattern in patterns.iter() {
+        collect_struct_fields(pattern, &mut struct_fields);
+    }
+    patterns
+        .iter()
+        .map(|pattern| with_struct_fields(pattern, &struct_fields))
+        .collect()
+}
+
+fn collect_struct_fields<'sc>(
+    pattern: &Pattern<'sc>,
+    struct_fields: &mut HashMap<&'sc str, BTreeSet<&'sc str>>,
+) {
+    match pattern {
+        Pattern::Wild => {}
+        Pattern::Constructor(constructor, fields) => {
+            if let Constructor::Struct(struct_name, field_names) = constructor {
+                struct_fields
+                    .entry(*struct_name)
+                    .or_default()
+                    .extend(field_names.iter().copied());
+            }
+            for field in fields.iter() {
+                collect_struct_fields(field, struct_fields);
+            }
+        }
+        Pattern::Or(alternatives) => {
+            for alternative in alternatives.iter() {
+                collect_struct_fields(alternative, struct_fields);
+            }
+        }
+    }
+}
+
+fn with_struct_fields<'sc>(
+    pattern: &Pattern<'sc>,
+    struct_fields: &HashMap<&'sc str, BTreeSet<&'sc str>>,
+) -> Pattern<'sc> {
+    match pattern {
+        Pattern::Wild => Pattern::Wild,
+        Pattern::Constructor(Constructor::Struct(struct_name, field_names), fields) => {
+            let all_field_names = struct_fields[struct_name]
+                .iter()
+                .copied()
+                .collect::<Vec<_>>();
+            let fields = all_field_names
+                .iter()
+                .map(
+                    |name| match field_names.iter().position(|other| other == name) {
+                        Some(i) => with_struct_fields(&fields[i], struct_fields),
+                        None => Pattern::Wild,
+                    },
+                )
+                .collect();
+            Pattern::Constructor(Constructor::Struct(struct_name, all_field_names), fields)
+        }
+        Pattern::Constructor(constructor, fields) => Pattern::Constructor(
+            constructor.clone(),
+            fields
+                .iter()
+                .map(|field| with_struct_fields(field, struct_fields))
+                .collect(),
+        ),
+        Pattern::Or(alternatives) => Pattern::Or(
+            alternatives
+                .iter()
+                .map(|alternative| with_struct_fields(alternative, struct_fields))
+                .collect(),
+        ),
+    }
+}
+
+fn is_irrefutable(pattern: &Pattern) -> bool {
+    is_useful(&[vec![pattern.clone()]], &[Pattern::Wild]).is_none()
+}
+
+// whether some value is matched by both patterns
+fn intersects(left: &Pattern, right: &Pattern) -> bool {
+    match (left, right) {
+        (Pattern::Wild, _) | (_, Pattern::Wild) => true,
+        (Pattern::Or(alternatives), other) | (other, Pattern::Or(alternatives)) => alternatives
+            .iter()
+            .any(|alternative| intersects(alternative, other)),
+        // lines the `..` up with the other tuple, then compares as usual
+        (Pattern::Constructor(Constructor::TupleRest(before, _), fields), other)
+        | (other, Pattern::Constructor(Constructor::TupleRest(before, _), fields)) => {
+            let arity = match other {
+                Pattern::Constructor(constructor, _) => constructor.arity().max(fields.len()),
+                _ => fields.len(),
+            };
+            let widened = Pattern::Constructor(
+                Constructor::Tuple(arity),
+                widen_rest(*before, fields, arity),
+            );
+            intersects(&widened, other)
+        }
+        // arrays of some length that both allow, with the `..` lined up
+        (Pattern::Constructor(Constructor::ArrayRest(before, after), fields), other)
+        | (other, Pattern::Constructor(Constructor::ArrayRest(before, after), fields)) => {
+            let length = match other {
+                Pattern::Constructor(Constructor::Array(length), _) => *length,
+                Pattern::Constructor(Constructor::ArrayRest(other_before, other_after), _) => {
+                    before.max(other_before) + after.max(other_after)
+                }
+                _ => return false,
+            };
+            if length < before + after {
+                return false;
+            }
+            let widened = Pattern::Constructor(
+                Constructor::Array(length),
+                widen_rest(*before, fields, length),
+            );
+            intersects(&widened, other)
+        }
+        (Pattern::Constructor(left, _), Pattern::Constructor(right, _))
+            if interval(left).is_some() && interval(right).is_some() =>
+        {
+            match (interval(left), interval(right)) {
+                (Some((left_low, left_high)), Some((right_low, right_high))) => {
+                    left_low <= right_high
+                        && right_low <= left_high
+                        && left_low <= left_high
+                        && right_low <= right_high
+                }
+                _ => false,
+            }
+        }
+        (
+            Pattern::Constructor(left_constructor, left_fields),
+            Pattern::Constructor(right_constructor, right_fields),
+        ) => {
+            left_constructor == right_constructor
+                && left_fields
+                    .iter()
+                    .zip(right_fields.iter())
+                    .all(|(left, right)| intersects(left, right))
+        }
+    }
+}
+
+/// If `vector` is useful with respect to `matrix`, returns a witness: a
+/// vector of patterns matched by `vector` and by no row of `matrix`.
+pub fn is_useful<'sc>(
+    matrix: &[Vec<Pattern<'sc>>],
+    vector: &[Pattern<'sc>],
+) -> Option<Vec<Pattern<'sc>>> {
+    let (head, tail) = match vector.split_first() {
+        Some(split) => split,
+        None if matrix.is_empty() => return Some(vec![]),
+        None => return None,
+    };
+    // a row with an or-pattern in front matches what any of the rows with
+    // one of its alternatives there would
+    if matrix
+        .iter()
+        .any(|row| matches!(row.first(), Some(Pattern::Or(_))))
+    {
+        return is_useful(&expand_or_rows(matrix), vector);
+    }
+    match head {
+        Pattern::Or(alternatives) => alternatives.iter().find_map(|alternative| {
+            let mut expanded_vector = vec![alternative.clone()];
+            expanded_vector.extend_from_slice(tail);
+            is_useful(matrix, &expanded_vector)
+        }),
+        Pattern::Constructor(Constructor::TupleRest(before, _), fields) => {
+            let mut rows = matrix.to_vec();
+            rows.push(vector.to_vec());
+            let arity = tuple_arity(&rows)
+                .expect("the vector is a tuple")
+                .max(fields.len());
+            let mut widened_vector = vec![Pattern::Constructor(
+                Constructor::Tuple(arity),
+                widen_rest(*before, fields, arity),
+            )];
+            widened_vector.extend_from_slice(tail);
+            is_useful(matrix, &widened_vector)
+        }
+        // a `..` stands for several lengths, and is useful if any of them is
+        Pattern::Constructor(constructor @ Constructor::ArrayRest(before, _), fields) => {
+            let mut seen = head_constructors(matrix);
+            seen.push(constructor.clone());
+            array_lengths(&seen)
+                .iter()
+                .filter(|length| covers(constructor, length))
+                .find_map(|length| {
+                    let mut specialized_vector = widen_rest(*before, fields, length.arity());
+                    specialized_vector.extend_from_slice(tail);
+                    let witness = is_useful(&specialize(matrix, length), &specialized_vector)?;
+                    Some(rebuild(length, witness))
+                })
+        }
+        // a range is split into pieces that each of the rows either covers
+        // completely or not at all, and is useful if any piece is
+        Pattern::Constructor(constructor @ Constructor::Range(..), _) => {
+            split_range(constructor, &head_constructors(matrix))
+                .iter()
+                .find_map(|piece| {
+                    let witness = is_useful(&specialize(matrix, piece), tail)?;
+                    Some(rebuild(piece, witness))
+                })
+        }
+        Pattern::Constructor(constructor, fields) => {
+            let mut specialized_vector = fields.clone();
+            specialized_vector.extend_from_slice(tail);
+            let witness = is_useful(&specialize(matrix, constructor), &specialized_vector)?;
+            Some(rebuild(constructor, witness))
+        }
+        Pattern::Wild => {
+            let seen = head_constructors(matrix);
+            let missing = missing_constructor(&seen);
+            // integer constructors can overlap, so they are compared piece by
+            // piece instead
+            let seen = match seen.first().and_then(integer_width) {
+                Some(width) => split_range(
+                    &Constructor::Range(
+                        with_integer_value(width, 0),
+                        with_integer_value(width, integer_max(width)),
+                    ),
+                    &seen,
+                ),
+                None if is_array(&seen) => array_lengths(&seen),
+                None => seen,
+            };
+            match missing {
+                // every constructor of the type appears in the first column, so
+                // the wildcard is useful iff it is useful for one of them
+                None => seen.iter().find_map(|constructor| {
+                    let mut specialized_vector = vec![Pattern::Wild; constructor.arity()];
+                    specialized_vector.extend_from_slice(tail);
+                    let witness = is_useful(&specialize(matrix, constructor), &specialized_vector)?;
+                    Some(rebuild(constructor, witness))
+                }),
+                // otherwise only the rows starting with a wildcard matter
+                Some(missing) => {
+                    let mut witness = is_useful(&default_matrix(matrix), tail)?;
+                    witness.insert(0, missing);
+                    Some(witness)
+                }
+            }
+        }
+    }
+}
+
+fn expand_or_rows<'sc>(matrix: &[Vec<Pattern<'sc>>]) -> Vec<Vec<Pattern<'sc>>> {
+    let mut expanded = vec![];
+    for row in matrix.iter() {
+        match row.split_first() {
+            Some((Pattern::Or(alternatives), tail)) => {
+                for alternative in alternatives.iter() {
+                    let mut new_row = vec![alternative.clone()];
+                    new_row.extend_from_slice(tail);
+                    expanded.extend(expand_or_rows(&[new_row]));
+                }
+            }
+            _ => expanded.push(row.clone()),
+        }
+    }
+    expanded
+}
+
+fn specialize<'sc>(
+    matrix: &[Vec<Pattern<'sc>>],
+    constructor: &Constructor<'sc>,
+) -> Vec<Vec<Pattern<'sc>>> {
+    let mut specialized = vec![];
+    for row in matrix.iter() {
+        let (head, tail) = row.split_first().expect("rows have the same length");
+        let mut new_row = match (head, constructor) {
+            (
+                Pattern::Constructor(Constructor::TupleRest(before, after), fields),
+                Constructor::Tuple(arity),
+            ) if before + after <= *arity => widen_rest(*before, fields, *arity),
+            (Pattern::Constructor(other @ Constructor::ArrayRest(before, _), fields), _)
+                if covers(other, constructor) =>
+            {
+                widen_rest(*before, fields, constructor.arity())
+            }
+            (Pattern::Constructor(other, fields), _) if covers(other, constructor) => {
+                fields.clone()
+            }
+            (Pattern::Constructor(..), _) => continue,
+            (Pattern::Wild, _) => vec![Pattern::Wild; constructor.arity()],
+            (Pattern::Or(_), _) => unreachable!("or-rows are expanded first"),
+        };
+        new_row.extend_from_slice(tail);
+        specialized.push(new_row);
+    }
+    specialized
+}
+
+fn default_matrix<'sc>(matrix: &[Vec<Pattern<'sc>>]) -> Vec<Vec<Pattern<'sc>>> {
+    matrix
+        .iter()
+        .filter(|row| row[0] == Pattern::Wild)
+        .map(|row| row[1..].to_vec())
+        .collect()
+}
+
+// folds the first `arity` patterns of a specialized witness back under
+// `constructor`
+fn rebuild<'sc>(
+    constructor: &Constructor<'sc>,
+    mut witness: Vec<Pattern<'sc>>,
+) -> Vec<Pattern<'sc>> {
+    let tail = witness.split_off(constructor.arity());
+    let mut rebuilt = vec![Pattern::Constructor(constructor.clone(), witness)];
+    rebuilt.extend(tail);
+    rebuilt
+}
+
+fn head_constructors<'sc>(matrix: &[Vec<Pattern<'sc>>]) -> Vec<Constructor<'sc>> {
+    let mut seen = vec![];
+    for row in matrix.iter() {
+        let constructor = match &row[0] {
+            Pattern::Wild => continue,
+            Pattern::Or(_) => unreachable!("or-rows are expanded first"),
+            Pattern::Constructor(Constructor::TupleRest(..), _) => {
+                Constructor::Tuple(tuple_arity(matrix).expect("the column has a tuple"))
+            }
+            Pattern::Constructor(constructor, _) => constructor.clone(),
+        };
+        if !seen.contains(&constructor) {
+            seen.push(constructor);
+        }
+    }
+    seen
+}
+
+// The arity of the tuples in the first column of `matrix`. If every one of
+// them has a `..` it isn't known, so it's taken to be wide enough that no
+// element before a `..` is also one after a `..`.
+fn tuple_arity(matrix: &[Vec<Pattern>]) -> Option<usize> {
+    let mut exact = None;
+    let mut widest = None;
+    for row in matrix.iter() {
+        match &row[0] {
+            Pattern::Constructor(Constructor::Tuple(arity), _) => {
+                exact = exact.max(Some(*arity));
+            }
+            Pattern::Constructor(Constructor::TupleRest(before, after), _) => {
+                let (widest_before, widest_after) = widest.unwrap_or((0, 0));
+                widest = Some((widest_before.max(*before), widest_after.max(*after)));
+            }
+            _ => {}
+        }
+    }
+    exact.or_else(|| widest.map(|(before, after)| before + after))
+}
+
+// the fields of a tuple pattern with a `..` after `before` elements, as a
+// pattern for tuples of `arity` elements
+fn widen_rest<'sc>(before: usize, fields: &[Pattern<'sc>], arity: usize) -> Vec<Pattern<'sc>> {
+    let mut widened = fields[..before].to_vec();
+    widened.extend(vec![Pattern::Wild; arity - fields.len()]);
+    widened.extend_from_slice(&fields[before..]);
+    widened
+}
+
+/// Returns a pattern for a value of the column's type that isn't covered by
+/// `seen`, or `None` if `seen` is the complete set of constructors.
+fn missing_constructor<'sc>(seen: &[Constructor<'sc>]) -> Option<Pattern<'sc>> {
+    let literal = |value| Some(Pattern::Constructor(Constructor::Literal(value), vec![]));
+    let literals = seen.iter().filter_map(|constructor| match constructor {
+        Constructor::Literal(value) => Some(value),
+        _ => None,
+    });
+    match seen.first() {
+        // nothing is known about the type, so nothing more precise than a
+        // wildcard can be reported
+        None => Some(Pattern::Wild),
+        // tuples and structs only have one constructor
+        Some(Constructor::Array(_)) | Some(Constructor::ArrayRest(..)) => array_lengths(seen)
+            .into_iter()
+            .find(|length| !seen.iter().any(|other| covers(other, length)))
+            .map(|length| {
+                let fields = vec![Pattern::Wild; length.arity()];
+                Pattern::Constructor(length, fields)
+            }),
+        Some(Constructor::Tuple(_))
+        | Some(Constructor::TupleRest(..))
+        | Some(Constructor::Struct(..)) => None,
+        // an enum that wasn't declared could have any other variant
+        Some(Constructor::Variant { variants, .. }) if variants.is_empty() => {
+            Some(Pattern::Wild)
+        }
+        Some(Constructor::Variant {
+            enum_name,
+            variants,
+            ..
+        }) => variants
+            .iter()
+            .find(|(name, _)| {
+                !seen.iter().any(|other| {
+                    matches!(other, Constructor::Variant { variant_name, .. } if variant_name == name)
+                })
+            })
+            .map(|(name, arity)| {
+                let constructor = Constructor::Variant {
+                    enum_name,
+                    variant_name: name,
+                    arity: *arity,
+                    variants: variants.clone(),
+                };
+                Pattern::Constructor(constructor, vec![Pattern::Wild; *arity])
+            }),
+        Some(Constructor::Literal(Literal::Boolean(_))) => {
+            let seen = literals.cloned().collect::<Vec<_>>();
+            [true, false]
+                .iter()
+                .map(|b| Literal::Boolean(*b))
+                .find(|b| !seen.contains(b))
+                .and_then(literal)
+        }
+        Some(Constructor::Literal(Literal::String(_))) => Some(Pattern::Wild),
+        Some(Constructor::Literal(lit)) | Some(Constructor::Range(lit, _)) => {
+            let mut intervals = seen.iter().filter_map(interval).collect::<Vec<_>>();
+            intervals.sort_unstable();
+            // the smallest value that might not be covered yet, if any
+            let mut next = Some(0);
+            for (low, high) in intervals.into_iter().filter(|(low, high)| low <= high) {
+                match next {
+                    Some(n) if low > n => break,
+                    Some(n) if high >= n => next = high.checked_add(1),
+                    _ => {}
+                }
+            }
+            next.filter(|n| *n <= integer_max(lit))
+                .map(|n| with_integer_value(lit, n))
+                .and_then(literal)
+        }
+    }
+}
+
+// the integers a constructor stands for, as an inclusive range
+fn interval(constructor: &Constructor) -> Option<(u64, u64)> {
+    match constructor {
+        Constructor::Literal(lit) => lit.integer_value().map(|n| (n, n)),
+        Constructor::Range(low, high) => low.integer_value().zip(high.integer_value()),
+        _ => None,
+    }
+}
+
+// a literal of the same width as the integers `constructor` stands for
+fn integer_width<'a, 'sc>(constructor: &'a Constructor<'sc>) -> Option<&'a Literal<'sc>> {
+    match constructor {
+        Constructor::Literal(lit) | Constructor::Range(lit, _) if lit.integer_value().is_some() => {
+            Some(lit)
+        }
+        _ => None,
+    }
+}
+
+// whether every value `inner` stands for is one `outer` stands for too
+fn covers(outer: &Constructor, inner: &Constructor) -> bool {
+    match (outer, inner) {
+        (Constructor::ArrayRest(before, after), Constructor::Array(length)) => {
+            before + after <= *length
+        }
+        (
+            Constructor::ArrayRest(before, after),
+            Constructor::ArrayRest(inner_before, inner_after),
+        ) => before <= inner_before && after <= inner_after,
+        _ => match (interval(outer), interval(inner)) {
+            (Some((outer_low, outer_high)), Some((inner_low, inner_high))) => {
+                outer_low <= inner_low && inner_high <= outer_high && inner_low <= inner_high
+            }
+            _ => outer == inner,
+        },
+    }
+}
+
+fn is_array(seen: &[Constructor]) -> bool {
+    matches!(
+        seen.first(),
+        Some(Constructor::Array(_)) | Some(Constructor::ArrayRest(..))
+    )
+}
+
+// Arrays can be of any length, but only finitely many lengths can be told
+// apart by the patterns in `seen`: each one up to the longest array any of
+// them needs, and then all the longer ones together, as a `..` with enough
+// elements on either side to line up with every pattern.
+fn array_lengths<'sc>(seen: &[Constructor<'sc>]) -> Vec<Constructor<'sc>> {
+    let mut shortest_unseen = 0;
+    let mut widest_before = 0;
+    let mut widest_after = 0;
+    for constructor in seen.iter() {
+        match constructor {
+            Constructor::Array(length) => shortest_unseen = shortest_unseen.max(length + 1),
+            Constructor::ArrayRest(before, after) => {
+                widest_before = widest_before.max(*before);
+                widest_after = widest_after.max(*after);
+            }
+            _ => {}
+        }
+    }
+    let longest = shortest_unseen.max(widest_before + widest_after);
+    (0..longest)
+        .map(Constructor::Array)
+        .chain(std::iter::once(Constructor::ArrayRest(
+            widest_before,
+            longest - widest_before,
+        )))
+        .collect()
+}
+
+// Cuts the range `constructor` into pieces at every place where one of
+// `others` starts or ends, so that each of them covers a piece either
+// completely or not at all. Pieces of a single value are literals.
+fn split_range<'sc>(
+    constructor: &Constructor<'sc>,
+    others: &[Constructor<'sc>],
+) -> Vec<Constructor<'sc>> {
+    let (width, (low, high)) = match (integer_width(constructor), interval(constructor)) {
+        (Some(width), Some(bounds)) => (width, bounds),
+        _ => return vec![constructor.clone()],
+    };
+    if low > high {
+        return vec![];
+    }
+    let mut starts = BTreeSet::new();
+    starts.insert(low);
+    for (other_low, other_high) in others.iter().filter_map(interval) {
+        if low < other_low && other_low <= high {
+            starts.insert(other_low);
+        }
+        if low <= other_high && other_high < high {
+            starts.insert(other_high + 1);
+        }
+    }
+    let starts = starts.into_iter().collect::<Vec<_>>();
+    starts
+        .iter()
+        .enumerate()
+        .map(|(i, start)| {
+            let end = starts.get(i + 1).map_or(high, |next| next - 1);
+            if *start == end {
+                Constructor::Literal(with_integer_value(width, *start))
+            } else {
+                Constructor::Range(
+                    with_integer_value(width, *start),
+                    with_integer_value(width, end),
+                )
+            }
+        })
+        .collect()
+}
+
+fn integer_max(lit: &Literal) -> u64 {
+    match lit {
+        Literal::U8(_) | Literal::Byte(_) => u8::MAX as u64,
+        Literal::U16(_) => u16::MAX as u64,
+        Literal::U32(_) => u32::MAX as u64,
+        _ => u64::MAX,
+    }
+}
+
+// the literal of the same width as `lit` holding `n`
+fn with_integer_value<'sc>(lit: &Literal<'sc>, n: u64) -> Literal<'sc> {
+    match lit {
+        Literal::U8(_) => Literal::U8(n as u8),
+        Literal::Byte(_) => Literal::Byte(n as u8),
+        Literal::U16(_) => Literal::U16(n as u16),
+        Literal::U32(_) => Literal::U32(n as u32),
+        _ => Literal::U64(n),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        error::DesugarWarning,
+        language::{constructors::*, Declaration, Literal, Namespace, Node},
+        usefulness::{check_exhaustiveness, check_redundancy},
+    };
+
+    #[test]
+    fn exhaustive_with_catchall() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(literal_scrutinee(u32_(5))),
+                literal(u32_(1)),
+            ),
+            match_branch(match_scrutinee_catchall(), literal(u32_(2))),
+        ];
+        assert_eq!(check_exhaustiveness(&branches, &namespace), None);
+    }
+
+    #[test]
+    fn missing_integer() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(literal_scrutinee(u32_(0))),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(literal_scrutinee(u32_(1))),
+                literal(u32_(2)),
+            ),
+        ];
+        let witness = check_exhaustiveness(&branches, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "2");
+    }
+
+    #[test]
+    fn missing_boolean_in_tuple() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    literal_scrutinee(boolean(true)),
+                    variable_scrutinee("x"),
+                ])),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    variable_scrutinee("x"),
+                    literal_scrutinee(boolean(true)),
+                ])),
+                literal(u32_(2)),
+            ),
+        ];
+        let witness = check_exhaustiveness(&branches, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "(false, false)");
+    }
+
+    #[test]
+    fn exhaustive_booleans_in_struct() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(struct_scrutinee(
+                    "Flags",
+                    vec![
+                        struct_scrutinee_field("a", literal_scrutinee(boolean(true))),
+                        struct_scrutinee_field("b", variable_scrutinee("b")),
+                    ],
+                )),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(struct_scrutinee(
+                    "Flags",
+                    vec![
+                        struct_scrutinee_field("a", literal_scrutinee(boolean(false))),
+                        struct_scrutinee_field("b", variable_scrutinee("b")),
+                    ],
+                )),
+                literal(u32_(2)),
+            ),
+        ];
+        assert_eq!(check_exhaustiveness(&branches, &namespace), None);
+    }
+
+    #[test]
+    fn exhaustive_struct_fields_in_any_order() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(struct_scrutinee(
+                    "Flags",
+                    vec![struct_scrutinee_field(
+                        "a",
+                        literal_scrutinee(boolean(true)),
+                    )],
+                )),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(struct_scrutinee(
+                    "Flags",
+                    vec![
+                        struct_scrutinee_field("b", variable_scrutinee("b")),
+                        struct_scrutinee_field("a", literal_scrutinee(boolean(false))),
+                    ],
+                )),
+                literal(u32_(2)),
+            ),
+        ];
+        assert_eq!(check_exhaustiveness(&branches, &namespace), None);
+    }
+
+    #[test]
+    fn missing_with_rest() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    literal_scrutinee(boolean(true)),
+                    rest_scrutinee(),
+                ])),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    variable_scrutinee("x"),
+                    variable_scrutinee("y"),
+                    literal_scrutinee(boolean(false)),
+                ])),
+                literal(u32_(2)),
+            ),
+        ];
+        let witness = check_exhaustiveness(&branches, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "(false, _, true)");
+    }
+
+    #[test]
+    fn missing_struct() {
+        let namespace = Namespace::default();
+        let branches = vec![match_branch(
+            match_scrutinee(struct_scrutinee(
+                "Point",
+                vec![
+                    struct_scrutinee_field("x", variable_scrutinee("x")),
+                    struct_scrutinee_field("y", literal_scrutinee(u32_(7))),
+                ],
+            )),
+            literal(u32_(1)),
+        )];
+        let witness = check_exhaustiveness(&branches, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "Point { x: _, y: 0 }");
+    }
+
+    #[test]
+    fn unreachable_after_catchall() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(match_scrutinee(variable_scrutinee("x")), literal(u32_(1))),
+            match_branch(
+                match_scrutinee(literal_scrutinee(u32_(5))),
+                literal(u32_(2)),
+            ),
+            match_branch(match_scrutinee_catchall(), literal(u32_(3))),
+        ];
+        let warnings = check_redundancy(&branches, &namespace);
+        assert_eq!(warnings.len(), 2);
+        assert!(warnings
+            .iter()
+            .all(|warning| matches!(warning, DesugarWarning::UnreachableArm { .. })));
+    }
+
+    #[test]
+    fn unreachable_when_covered_by_several_arms() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(literal_scrutinee(boolean(true))),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(literal_scrutinee(boolean(false))),
+                literal(u32_(2)),
+            ),
+            match_branch(match_scrutinee(variable_scrutinee("b")), literal(u32_(3))),
+        ];
+        assert!(matches!(
+            check_redundancy(&branches, &namespace)[..],
+            [DesugarWarning::UnreachableArm { .. }]
+        ));
+    }
+
+    #[test]
+    fn partially_shadowed() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    variable_scrutinee("x"),
+                    literal_scrutinee(u32_(0)),
+                ])),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    literal_scrutinee(u32_(0)),
+                    variable_scrutinee("y"),
+                ])),
+                literal(u32_(2)),
+            ),
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    literal_scrutinee(u32_(1)),
+                    literal_scrutinee(u32_(1)),
+                ])),
+                literal(u32_(3)),
+            ),
+            match_branch(match_scrutinee_catchall(), literal(u32_(4))),
+        ];
+        assert!(matches!(
+            check_redundancy(&branches, &namespace)[..],
+            [DesugarWarning::PartiallyShadowedArm { .. }]
+        ));
+    }
+
+    #[test]
+    fn exhaustive_with_or() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            match_branch(
+                match_scrutinee(tuple_scrutinee(vec![
+                    or_scrutinee(vec![
+                        literal_scrutinee(boolean(true)),
+                        literal_scrutinee(boolean(false)),
+                    ]),
+                    literal_scrutinee(boolean(true)),
+                ])),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(or_scrutinee(vec![
+                    tuple_scrutinee(vec![
+                        wildcard_scrutinee(),
+                        literal_scrutinee(boolean(false)),
+                    ]),
+                    tuple_scrutinee(vec![literal_scrutinee(boolean(true)), wildcard_scrutinee()]),
+                ])),
+                literal(u32_(2)),
+            ),
+        ];
+        assert_eq!(check_exhaustiveness(&branches, &namespace), None);
+        assert_eq!(check_redundancy(&branches, &namespace).len(), 1);
+    }
+
+    #[test]
+    fn guarded_arms_are_not_exhaustive() {
+        let namespace = Namespace::default();
+        let branches = vec![
+            guarded_match_branch(
+                match_scrutinee(variable_scrutinee("x")),
+                literal(boolean(true)),
+                literal(u32_(1)),
+            ),
+            match_branch(
+                match_scrutinee(literal_scrutinee(boolean(true))),
+                literal(u32_(2)),
+            ),
+        ];
+        let witness = check_exhaustiveness(&branches, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "false");
+    }
+
+    #[test]
+    fn ranges() {
+        let namespace = Namespace::default();
+        let arm = |start: Option<u8>, end: Option<u8>| {
+            match_branch(
+                match_scrutinee(range_scrutinee(
+                    start.map(Literal::U8),
+                    end.map(Literal::U8),
+                    true,
+                )),
+                literal(u32_(0)),
+            )
+        };
+        let exhaustive = vec![
+            arm(Some(0), Some(9)),
+            arm(Some(10), Some(200)),
+            arm(Some(201), None),
+        ];
+        assert_eq!(check_exhaustiveness(&exhaustive, &namespace), None);
+        assert_eq!(check_redundancy(&exhaustive, &namespace), vec![]);
+
+        let gap = vec![arm(None, Some(9)), arm(Some(11), None)];
+        let witness = check_exhaustiveness(&gap, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "10");
+
+        // covered by two ranges together, and by one partially
+        let overlapping = vec![
+            arm(Some(0), Some(9)),
+            arm(Some(5), Some(20)),
+            arm(Some(3), Some(12)),
+            match_branch(
+                match_scrutinee(literal_scrutinee(Literal::U8(21))),
+                literal(u32_(0)),
+            ),
+            arm(Some(15), None),
+        ];
+        let warnings = check_redundancy(&overlapping, &namespace);
+        assert!(matches!(
+            warnings.as_slice(),
+            [
+                DesugarWarning::PartiallyShadowedArm { .. },
+                DesugarWarning::UnreachableArm { .. },
+                DesugarWarning::PartiallyShadowedArm { .. },
+            ]
+        ));
+        assert_eq!(check_exhaustiveness(&overlapping, &namespace), None);
+    }
+
+    #[test]
+    fn arrays() {
+        let namespace = Namespace::default();
+        let arm = |elems| match_branch(match_scrutinee(array_scrutinee(elems)), literal(u32_(0)));
+        let exhaustive = vec![
+            arm(vec![]),
+            arm(vec![variable_scrutinee("x")]),
+            arm(vec![
+                variable_scrutinee("first"),
+                rest_scrutinee(),
+                variable_scrutinee("last"),
+            ]),
+        ];
+        assert_eq!(check_exhaustiveness(&exhaustive, &namespace), None);
+
+        let missing_one = vec![
+            arm(vec![]),
+            arm(vec![
+                wildcard_scrutinee(),
+                wildcard_scrutinee(),
+                rest_scrutinee(),
+            ]),
+        ];
+        let witness = check_exhaustiveness(&missing_one, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "[_]");
+
+        let overlapping = vec![
+            arm(vec![literal_scrutinee(u32_(0)), rest_scrutinee()]),
+            arm(vec![rest_scrutinee(), literal_scrutinee(u32_(0))]),
+            arm(vec![literal_scrutinee(u32_(0)), wildcard_scrutinee()]),
+            arm(vec![rest_scrutinee()]),
+        ];
+        let warnings = check_redundancy(&overlapping, &namespace);
+        assert!(matches!(
+            warnings.as_slice(),
+            [
+                DesugarWarning::PartiallyShadowedArm { .. },
+                DesugarWarning::UnreachableArm { .. },
+            ]
+        ));
+    }
+
+    #[test]
+    fn enums() {
+        let mut namespace = Namespace::default();
+        namespace.declare_enum(
+            match enum_declaration(
+                "Shape",
+                vec![
+                    ("Dot", vec![]),
+                    ("Circle", vec!["u32"]),
+                    ("Rect", vec!["u32", "u32"]),
+                ],
+            ) {
+                Node::Declaration(Declaration::EnumDeclaration(declaration)) => declaration,
+                _ => unreachable!(),
+            },
+        );
+        let arm = |variant, payload| {
+            match_branch(
+                match_scrutinee(enum_scrutinee("Shape", variant, payload)),
+                literal(u32_(0)),
+            )
+        };
+        let exhaustive = vec![
+            arm("Dot", vec![]),
+            arm("Circle", vec![wildcard_scrutinee()]),
+            arm("Rect", vec![wildcard_scrutinee(), wildcard_scrutinee()]),
+        ];
+        assert_eq!(check_exhaustiveness(&exhaustive, &namespace), None);
+
+        let missing_one = vec![
+            arm("Dot", vec![]),
+            arm("Rect", vec![wildcard_scrutinee(), wildcard_scrutinee()]),
+        ];
+        let witness = check_exhaustiveness(&missing_one, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "Shape::Circle(_)");
+
+        let missing_payload = vec![
+            arm("Dot", vec![]),
+            arm("Circle", vec![literal_scrutinee(u32_(0))]),
+            arm("Rect", vec![wildcard_scrutinee(), wildcard_scrutinee()]),
+            arm("Circle", vec![literal_scrutinee(u32_(0))]),
+        ];
+        let witness = check_exhaustiveness(&missing_payload, &namespace).unwrap();
+        assert_eq!(witness.to_string(), "Shape::Circle(1)");
+        assert!(matches!(
+            check_redundancy(&missing_payload, &namespace)[..],
+            [DesugarWarning::UnreachableArm { .. }]
+        ));
+    }
+}
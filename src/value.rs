@@ -0,0 +1,245 @@
+use std::convert::TryFrom;
+use std::fmt;
+
+use crate::language::*;
+use crate::span::Span;
+
+/// A fully evaluated runtime value.
+///
+/// Unlike an `Expression`, a `Value` never needs further evaluation, so it is
+/// what the interpreter stores in its `Namespace` and what the matcher
+/// inspects when it is given a concrete scrutinee.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value<'sc> {
+    U8(u8),
+    U16(u16),
+    U32(u32),
+    U64(u64),
+    String(&'sc str),
+    Boolean(bool),
+    Byte(u8),
+    Unit,
+    Tuple(Vec<Value<'sc>>),
+    Array(Vec<Value<'sc>>),
+    Struct {
+        struct_name: &'sc str,
+        fields: Vec<(&'sc str, Value<'sc>)>,
+    },
+    Enum {
+        enum_name: &'sc str,
+        variant_name: &'sc str,
+        payload: Vec<Value<'sc>>,
+    },
+}
+
+impl<'sc> Value<'sc> {
+    pub fn integer_value(&self) -> Option<u64> {
+        match self {
+            Value::U8(n) | Value::Byte(n) => Some(*n as u64),
+            Value::U16(n) => Some(*n as u64),
+            Value::U32(n) => Some(*n as u64),
+            Value::U64(n) => Some(*n),
+            _ => None,
+        }
+    }
+}
+
+impl<'sc> From<Literal<'sc>> for Value<'sc> {
+    fn from(lit: Literal<'sc>) -> Self {
+        match lit {
+            Literal::U8(n) => Value::U8(n),
+            Literal::U16(n) => Value::U16(n),
+            Literal::U32(n) => Value::U32(n),
+            Literal::U64(n) => Value::U64(n),
+            Literal::String(s) => Value::String(s),
+            Literal::Boolean(b) => Value::Boolean(b),
+            Literal::Byte(b) => Value::Byte(b),
+        }
+    }
+}
+
+/// Converts an expression that is already a value, i.e. one built only out of
+/// literals, units, tuples, arrays, structs and enums. Anything that still
+/// needs to be evaluated is handed back as the error.
+impl<'sc> TryFrom<Expression<'sc>> for Value<'sc> {
+    type Error = Expression<'sc>;
+
+    fn try_from(exp: Expression<'sc>) -> Result<Self, Self::Error> {
+        match exp {
+            Expression::Literal { value, .. } => Ok(Value::from(value)),
+            Expression::Unit { .. } => Ok(Value::Unit),
+            Expression::Tuple { elems, .. } => {
+                let mut values = vec![];
+                for elem in elems.into_iter() {
+                    values.push(Value::try_from(elem)?);
+                }
+                Ok(Value::Tuple(values))
+            }
+            Expression::Array { contents, .. } => {
+                let mut values = vec![];
+                for elem in contents.into_iter() {
+                    values.push(Value::try_from(elem)?);
+                }
+                Ok(Value::Array(values))
+            }
+            Expression::StructExpression {
+                struct_name,
+                fields,
+                ..
+            } => {
+                let mut values = vec![];
+                for field in fields.into_iter() {
+                    values.push((field.name.primary_name, Value::try_from(field.value)?));
+                }
+                Ok(Value::Struct {
+                    struct_name: struct_name.primary_name,
+                    fields: values,
+                })
+            }
+            Expression::EnumInstantiation {
+                enum_name,
+                variant_name,
+                payload,
+                ..
+            } => {
+                let mut values = vec![];
+                for elem in payload.into_iter() {
+                    values.push(Value::try_from(elem)?);
+                }
+                Ok(Value::Enum {
+                    enum_name: enum_name.primary_name,
+                    variant_name: variant_name.primary_name,
+                    payload: values,
+                })
+            }
+            exp => Err(exp),
+        }
+    }
+}
+
+impl<'sc> From<Value<'sc>> for Expression<'sc> {
+    fn from(value: Value<'sc>) -> Self {
+        let span = Span::dummy();
+        let literal = |value| Expression::Literal { value, span };
+        match value {
+            Value::U8(n) => literal(Literal::U8(n)),
+            Value::U16(n) => literal(Literal::U16(n)),
+            Value::U32(n) => literal(Literal::U32(n)),
+            Value::U64(n) => literal(Literal::U64(n)),
+            Value::String(s) => literal(Literal::String(s)),
+            Value::Boolean(b) => literal(Literal::Boolean(b)),
+            Value::Byte(b) => literal(Literal::Byte(b)),
+            Value::Unit => Expression::Unit { span },
+            Value::Tuple(values) => Expression::Tuple {
+                elems: values.into_iter().map(Expression::from).collect(),
+                span,
+            },
+            Value::Array(values) => Expression::Array {
+                contents: values.into_iter().map(Expression::from).collect(),
+                span,
+            },
+            Value::Struct {
+                struct_name,
+                fields,
+            } => Expression::StructExpression {
+                struct_name: Ident::new(struct_name, span),
+                fields: fields
+                    .into_iter()
+                    .map(|(name, value)| StructExpressionField {
+                        name: Ident::new(name, span),
+                        value: Expression::from(value),
+                    })
+                    .collect(),
+                span,
+            },
+            Value::Enum {
+                enum_name,
+                variant_name,
+                payload,
+            } => Expression::EnumInstantiation {
+                enum_name: Ident::new(enum_name, span),
+                variant_name: Ident::new(variant_name, span),
+                payload: payload.into_iter().map(Expression::from).collect(),
+                span,
+            },
+        }
+    }
+}
+
+impl<'sc> fmt::Display for Value<'sc> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::U8(n) => write!(f, "{}", n),
+            Value::U16(n) => write!(f, "{}", n),
+            Value::U32(n) => write!(f, "{}", n),
+            Value::U64(n) => write!(f, "{}", n),
+            Value::String(s) => write!(f, "{}", s),
+            Value::Boolean(b) => write!(f, "{}", b),
+            Value::Byte(b) => write!(f, "{}", b),
+            Value::Unit => write!(f, "()"),
+            Value::Tuple(values) => {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "({})", values.join(", "))
+            }
+            Value::Array(values) => {
+                let values = values.iter().map(Value::to_string).collect::<Vec<_>>();
+                write!(f, "[{}]", values.join(", "))
+            }
+            Value::Struct {
+                struct_name,
+                fields,
+            } => {
+                let fields = fields
+                    .iter()
+                    .map(|(name, value)| format!("{}: {}", name, value))
+                    .collect::<Vec<_>>();
+                write!(f, "{} {{ {} }}", struct_name, fields.join(", "))
+            }
+            Value::Enum {
+                enum_name,
+                variant_name,
+                payload,
+            } => {
+                write!(f, "{}::{}", enum_name, variant_name)?;
+                if !payload.is_empty() {
+                    let payload = payload.iter().map(Value::to_string).collect::<Vec<_>>();
+                    write!(f, "({})", payload.join(", "))?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{language::constructors::*, value::Value};
+
+    use std::convert::TryFrom;
+
+    #[test]
+    fn value_from_expression() {
+        let exp = tuple(vec![
+            literal(u32_(2)),
+            struct_("Point", vec![struct_field("x", literal(boolean(true)))]),
+        ]);
+        let value = Value::try_from(exp.clone()).unwrap();
+        assert_eq!(
+            value,
+            Value::Tuple(vec![
+                Value::U32(2),
+                Value::Struct {
+                    struct_name: "Point",
+                    fields: vec![("x", Value::Boolean(true))],
+                },
+            ])
+        );
+        assert_eq!(exp, value.into());
+    }
+
+    #[test]
+    fn value_from_unevaluated_expression() {
+        let exp = tuple(vec![literal(u32_(2)), variable("x")]);
+        assert_eq!(Value::try_from(exp), Err(variable("x")));
+    }
+}